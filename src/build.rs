@@ -0,0 +1,244 @@
+use std::path::PathBuf;
+
+use markdown::mdast::{Node, Paragraph, Root, Text};
+
+use crate::md;
+use crate::slide::*;
+use crate::write;
+
+// Decks built in code rather than read from a file. The result is the same as
+// parsing the matching directives, e.g.
+//
+//   Deck::new()
+//       .title("Weekly performance")
+//       .slide("Results", |s| {
+//           s.grid(40, |c| c.text("Throughput: 1.2M req/s").col().image("bench.png"))
+//       })
+//
+// is the deck of
+//
+//   .TTL Weekly performance
+//   .SLD Results
+//   .GRD 40
+//   Throughput: 1.2M req/s
+//   .COL
+//   .IMG bench.png
+//   .GRD end
+//
+// Hyphenation dictionaries can only be loaded by the parser.
+impl Deck {
+    pub fn new() -> Deck {
+        Deck::default()
+    }
+
+    pub fn padding(mut self, padding: usize) -> Deck {
+        self.config.padding = padding;
+        self
+    }
+
+    pub fn margin(mut self, middle: usize, image: impl Into<PathBuf>) -> Deck {
+        self.config.margin = Some(Margin { image: image.into(), middle });
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Deck {
+        self.config.align = align;
+        self
+    }
+
+    pub fn tracking(mut self, tracking: usize) -> Deck {
+        self.config.tracking = tracking;
+        self
+    }
+
+    pub fn kerning(mut self, kerning: bool) -> Deck {
+        self.config.kerning = kerning;
+        self
+    }
+
+    pub fn fit(mut self, fit: FitMode) -> Deck {
+        self.config.fit = fit;
+        self
+    }
+
+    // Left, center and right segments, as with .HDR.
+    pub fn header(mut self, segments: &[&str]) -> Deck {
+        self.config.header = Some(segments.iter().map(|s| s.to_string()).collect());
+        self
+    }
+
+    pub fn footer(mut self, segments: &[&str]) -> Deck {
+        self.config.footer = Some(segments.iter().map(|s| s.to_string()).collect());
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> Deck {
+        self.meta.title = Some(title.to_string());
+        self
+    }
+
+    pub fn author(mut self, author: &str) -> Deck {
+        self.meta.author = Some(author.to_string());
+        self
+    }
+
+    pub fn date(mut self, date: &str) -> Deck {
+        self.meta.date = Some(date.to_string());
+        self
+    }
+
+    pub fn event(mut self, event: &str) -> Deck {
+        self.meta.event = Some(event.to_string());
+        self
+    }
+
+    // An empty title carries over the previous slide's, as with a bare .SLD.
+    pub fn slide(mut self, title: &str, f: impl FnOnce(SlideBuilder) -> SlideBuilder) -> Deck {
+        let title = match title {
+            "" => self.slides.last().and_then(|s| s.title.clone()),
+            t => Some(t.to_string()),
+        };
+        let s = f(SlideBuilder {
+            slide: Slide {
+                title,
+                content: Vec::new(),
+                fit: None,
+                layout: None,
+                header: true,
+                footer: true,
+                hidden: false,
+            },
+        });
+        self.slides.push(s.slide);
+        self
+    }
+
+    // The deck as a deck file, that parses back into the same deck.
+    pub fn to_eimd(&self) -> String {
+        write::deck(self)
+    }
+
+    // Same as to_eimd, but collecting problems rather than printing them.
+    pub fn to_eimd_with(&self, diags: &mut Vec<md::Diag>) -> String {
+        write::deck_with(self, diags)
+    }
+}
+
+// Markdown source, e.g. "- **40%** faster".
+fn markdown(src: &str) -> Content {
+    Content::Md(md::to_mdast(src))
+}
+
+// Plain text, shown as is, without any markdown in it taking effect.
+fn text(value: &str) -> Content {
+    let text = Node::Text(Text { value: value.to_string(), position: None });
+    let para = Node::Paragraph(Paragraph { children: vec![text], position: None });
+    Content::Md(Node::Root(Root { children: vec![para], position: None }))
+}
+
+pub struct SlideBuilder {
+    slide: Slide,
+}
+
+impl SlideBuilder {
+    // Content goes into the open column of a .LAY two-column grid, if any, as
+    // it does when parsing.
+    fn push(mut self, content: Content) -> SlideBuilder {
+        match self.slide.content.last_mut() {
+            Some(Content::Grid(g)) if !g.closed => g.column().push(content),
+            _ => self.slide.content.push(content),
+        }
+        self
+    }
+
+    pub fn markdown(self, src: &str) -> SlideBuilder {
+        self.push(markdown(src))
+    }
+
+    pub fn text(self, value: &str) -> SlideBuilder {
+        self.push(text(value))
+    }
+
+    pub fn image(self, path: impl Into<PathBuf>) -> SlideBuilder {
+        self.push(Content::Img(path.into()))
+    }
+
+    pub fn align(self, align: Align) -> SlideBuilder {
+        self.push(Content::Align(align))
+    }
+
+    pub fn grid(self, ratio: usize, f: impl FnOnce(GridBuilder) -> GridBuilder) -> SlideBuilder {
+        let mut grid = f(GridBuilder { grid: Grid::new(ratio) }).grid;
+        grid.closed = true;
+        self.push(Content::Grid(grid))
+    }
+
+    // Move on to the second column of a .LAY two-column slide.
+    pub fn col(mut self) -> SlideBuilder {
+        match self.slide.content.last_mut() {
+            Some(Content::Grid(g)) if !g.closed => g.split = true,
+            _ => {}
+        }
+        self
+    }
+
+    pub fn layout(mut self, layout: SlideLayout) -> SlideBuilder {
+        self.slide.layout = Some(layout);
+        if layout == SlideLayout::TwoColumn {
+            return self.push(Content::Grid(Grid::new(50)));
+        }
+        self
+    }
+
+    pub fn fit(mut self, fit: FitMode) -> SlideBuilder {
+        self.slide.fit = Some(fit);
+        self
+    }
+
+    pub fn no_header(mut self) -> SlideBuilder {
+        self.slide.header = false;
+        self
+    }
+
+    pub fn no_footer(mut self) -> SlideBuilder {
+        self.slide.footer = false;
+        self
+    }
+
+    pub fn hidden(mut self) -> SlideBuilder {
+        self.slide.hidden = true;
+        self
+    }
+}
+
+pub struct GridBuilder {
+    grid: Grid,
+}
+
+impl GridBuilder {
+    fn push(mut self, content: Content) -> GridBuilder {
+        self.grid.column().push(content);
+        self
+    }
+
+    pub fn markdown(self, src: &str) -> GridBuilder {
+        self.push(markdown(src))
+    }
+
+    pub fn text(self, value: &str) -> GridBuilder {
+        self.push(text(value))
+    }
+
+    pub fn image(self, path: impl Into<PathBuf>) -> GridBuilder {
+        self.push(Content::Img(path.into()))
+    }
+
+    pub fn align(self, align: Align) -> GridBuilder {
+        self.push(Content::Align(align))
+    }
+
+    pub fn col(mut self) -> GridBuilder {
+        self.grid.split = true;
+        self
+    }
+}
@@ -0,0 +1,216 @@
+use markdown::mdast::Node;
+use sdl2::image::InitFlag;
+
+use crate::cli::Options;
+use eileda::layout::{H, W};
+use eileda::md::{self, diag, Diag, Item, Loc};
+use eileda::slide::{Deck, Presentation, SlideLayout};
+
+// Check a deck for problems without presenting it. Exits nonzero if there are
+// any, so that it can be used in hooks and scripts.
+pub fn main(opts: &Options) -> i32 {
+    let mut diags = Vec::new();
+    let items = md::lex_with(&opts.file, &mut diags);
+    lint(&items, &mut diags);
+    let deck = md::parse_with(&items, &mut diags);
+
+    // Overflow needs the deck loaded for real, but not shown.
+    sdl2::hint::set("SDL_VIDEODRIVER", "dummy");
+    if let Err(e) = load(opts, &items, deck, &mut diags) {
+        eprintln!("Couldn't load deck, skipping layout checks: {}", e);
+    }
+
+    diags.sort_by(|a, b| (&a.loc.file, a.loc.line).cmp(&(&b.loc.file, b.loc.line)));
+    if opts.json {
+        println!("{}", to_json(&diags));
+    } else {
+        for d in &diags {
+            println!("{}", d);
+        }
+    }
+
+    if diags.is_empty() { 0 } else { 1 }
+}
+
+fn load(opts: &Options, items: &[(Loc, Item)], deck: Deck, diags: &mut Vec<Diag>) -> Result<(), String> {
+    let sdl_context = sdl2::init()?;
+    let _image_context = sdl2::image::init(InitFlag::PNG | InitFlag::JPG)?;
+    let font_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let video_subsystem = sdl_context.video()?;
+
+    let window = video_subsystem
+        .window("eileda", (W * 2) as u32, (H * 2) as u32)
+        .hidden()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let canvas = window.into_canvas().software().build().map_err(|e| e.to_string())?;
+    let texture_creator = canvas.texture_creator();
+
+    // Deck defaults rather than the user's settings, so that results don't
+    // depend on who runs the check.
+    let mut p = Presentation::load(deck, &texture_creator, &font_context);
+    p.config.ttf |= opts.ttf;
+
+    let starts = items.iter().filter(|(_, i)| matches!(i, Item::BeginSlide(_)));
+    for (i, (loc, _)) in starts.enumerate().take(p.slides.len()) {
+        let over = p.overflow(i);
+        if over > 0 {
+            let title = p.slides[i].title.as_deref().unwrap_or("untitled");
+            let msg = format!("Slide {} ({}) overflows by {}px", i + 1, title, over);
+            diag(diags, loc, "overflow", msg);
+        }
+    }
+    Ok(())
+}
+
+// Problems with how directives fit together, which the parser lets slide.
+fn lint(items: &[(Loc, Item)], diags: &mut Vec<Diag>) {
+    // Start of the current slide, whether it has a title and content, and
+    // start of the grid it's in, if any, and whether that grid needs closing.
+    // The grid of a two-column layout ends with its slide.
+    let mut slide: Option<(&Loc, bool, bool)> = None;
+    let mut grid: Option<(&Loc, bool)> = None;
+
+    for (loc, item) in items {
+        match item {
+            Item::BeginSlide(t) => {
+                end_slide(slide, grid, diags);
+                slide = Some((loc, t.is_some(), false));
+                grid = None;
+            }
+            Item::BeginGrid(_) | Item::Layout(SlideLayout::TwoColumn) => {
+                if grid.is_some() {
+                    diag(diags, loc, "nested-grid", "Grids can't be nested".to_string());
+                }
+                grid = Some((loc, matches!(item, Item::BeginGrid(_))));
+                if let (Item::Layout(_), Some((_, _, content))) = (item, &mut slide) {
+                    *content = true;
+                }
+            }
+            Item::EndGrid => {
+                if grid.is_none() {
+                    diag(diags, loc, "unopened-grid", ".GRD end outside of a grid".to_string());
+                }
+                grid = None;
+            }
+            Item::Md(md) if !md::is_blank(md) => {
+                if let Some((_, _, content)) = &mut slide {
+                    *content = true;
+                }
+                unsupported(md, loc, diags);
+            }
+            Item::Img(_) => {
+                if let Some((_, _, content)) = &mut slide {
+                    *content = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    end_slide(slide, grid, diags);
+}
+
+fn end_slide(slide: Option<(&Loc, bool, bool)>, grid: Option<(&Loc, bool)>, diags: &mut Vec<Diag>) {
+    if let Some((loc, true)) = grid {
+        diag(diags, loc, "unclosed-grid", "Grid is never closed with .GRD end".to_string());
+    }
+    if let Some((loc, false, false)) = slide {
+        diag(diags, loc, "empty-slide", "Slide has no title and no content".to_string());
+    }
+}
+
+// Markdown that the layout doesn't know how to show.
+fn unsupported(md: &Node, loc: &Loc, diags: &mut Vec<Diag>) {
+    match md {
+        Node::Root(_)
+        | Node::Paragraph(_)
+        | Node::Heading(_)
+        | Node::Strong(_)
+        | Node::Emphasis(_)
+        | Node::List(_)
+        | Node::ListItem(_)
+        | Node::BlockQuote(_)
+        | Node::Text(_) => {
+            for c in md.children().into_iter().flatten() {
+                unsupported(c, loc, diags);
+            }
+        }
+        n => {
+            let name = format!("{:?}", n);
+            let name = name.split(|c: char| !c.is_alphanumeric()).next().unwrap_or("");
+            let line = loc.line + n.position().map(|p| p.start.line - 1).unwrap_or(0);
+            let at = Loc { file: loc.file.clone(), line };
+            diag(diags, &at, "unsupported-markdown", format!("Unsupported markdown: {}", name));
+        }
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn to_json(diags: &[Diag]) -> String {
+    let entries = diags
+        .iter()
+        .map(|d| {
+            format!(
+                "  {{\"file\": {}, \"line\": {}, \"code\": {}, \"message\": {}}}",
+                json_str(&d.loc.file),
+                d.loc.line,
+                json_str(d.code),
+                json_str(&d.msg),
+            )
+        })
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[\n{}\n]", entries.join(",\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: usize) -> Loc {
+        Loc { file: "deck \"a\".eimd".into(), line }
+    }
+
+    #[test]
+    fn reports_code_blocks_as_json() {
+        let items = vec![
+            (at(1), Item::BeginSlide(Some("Code".to_string()))),
+            (at(2), Item::Md(md::to_mdast("Some text\n\n```\nfn main() {}\n```"))),
+        ];
+        let mut diags = Vec::new();
+        lint(&items, &mut diags);
+
+        assert_eq!(diags.len(), 1);
+        assert_eq!((diags[0].code, diags[0].loc.line), ("unsupported-markdown", 4));
+        assert_eq!(
+            to_json(&diags),
+            "[\n  {\"file\": \"deck \\\"a\\\".eimd\", \"line\": 4, \"code\": \"unsupported-markdown\", \
+             \"message\": \"Unsupported markdown: Code\"}\n]"
+        );
+    }
+
+    #[test]
+    fn reports_nothing_as_empty_json() {
+        let items = vec![(at(1), Item::BeginSlide(Some("Fine".to_string())))];
+        let mut diags = Vec::new();
+        lint(&items, &mut diags);
+        assert_eq!(to_json(&diags), "[]");
+    }
+}
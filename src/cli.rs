@@ -0,0 +1,106 @@
+use crate::config;
+
+pub struct Options {
+    pub file: String,
+    pub start: Option<usize>,
+    pub fullscreen: bool,
+    pub size: Option<(u32, u32)>,
+    pub ttf: bool,
+    pub display: Option<i32>,
+    pub hidden: bool,
+    pub json: bool,
+}
+
+pub enum Command {
+    Present(Options),
+    Export(Options, String),
+    Check(Options),
+    Fonts(Vec<String>),
+    Help,
+    Version,
+}
+
+pub fn usage(prog: &str) -> String {
+    format!(
+        "Usage: {0} [present] [options] <file.eimd>
+       {0} export [--ttf] [--hidden] <file.eimd> <out_dir>
+       {0} check [--json] <file.eimd>
+       {0} fonts [name out.png [sample]]
+       {0} fonts convert <in.ttf> <px> <out.uf2>
+
+Options:
+  --start <n>       Start at slide n.
+  --fullscreen      Start in fullscreen.
+  --size <w>x<h>    Window size.
+  --ttf             Start with TTF fonts.
+  --display <n>     Open on display n, counting from 0.
+  --hidden          Include hidden slides.
+  -h, --help        Show this help.
+  -V, --version     Show the version.
+",
+        prog
+    )
+}
+
+// A deck file on its own is the same as "present" with it.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let (cmd, args) = match args.first().map(|a| a.as_str()) {
+        Some("fonts") => return Ok(Command::Fonts(args[1..].to_vec())),
+        Some(c @ ("present" | "export" | "check")) => (c, &args[1..]),
+        _ => ("present", args),
+    };
+
+    let mut opts = Options {
+        file: String::new(),
+        start: None,
+        fullscreen: false,
+        size: None,
+        ttf: false,
+        display: None,
+        hidden: false,
+        json: false,
+    };
+    let mut positional = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().cloned().ok_or(format!("Missing value for {}", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--fullscreen" => opts.fullscreen = true,
+            "--ttf" => opts.ttf = true,
+            "--hidden" => opts.hidden = true,
+            "--json" if cmd == "check" => opts.json = true,
+            "--start" => {
+                let v = value(arg)?;
+                match v.parse() {
+                    Ok(n) if n > 0 => opts.start = Some(n),
+                    _ => return Err(format!("Bad slide number: {}", v)),
+                }
+            }
+            "--size" => {
+                let v = value(arg)?;
+                opts.size = Some(config::parse_size(&v).ok_or(format!("Bad window size: {}", v))?);
+            }
+            "--display" => {
+                let v = value(arg)?;
+                opts.display = Some(v.parse().map_err(|_| format!("Bad display: {}", v))?);
+            }
+            a if a.starts_with('-') && a.len() > 1 => return Err(format!("Unknown option: {}", a)),
+            a => positional.push(a.to_string()),
+        }
+    }
+
+    let want = if cmd == "export" { 2 } else { 1 };
+    if positional.len() != want {
+        return Err(format!("Wrong number of arguments for {}", cmd));
+    }
+    opts.file = positional.remove(0);
+
+    Ok(match cmd {
+        "export" => Command::Export(opts, positional.remove(0)),
+        "check" => Command::Check(opts),
+        _ => Command::Present(opts),
+    })
+}
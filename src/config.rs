@@ -0,0 +1,117 @@
+use sdl2::keyboard::Keycode;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::keys::{self, Action};
+use eileda::slide::{Presentation, Theme};
+
+// User settings, read from $XDG_CONFIG_HOME/eileda/config (by default,
+// ~/.config/eileda/config). One setting per line; lines starting with # are
+// ignored.
+//
+//   bind <key> <action>   Bind a key, by its SDL name, e.g. "bind l next" or
+//                         "bind Page Down next".
+//   unbind <key>          Remove the bindings of a key.
+//   size <w>x<h>          Window size.
+//   theme light|dark      Slide colors.
+//   renderer ufx|ttf      Whether to start with bitmap or TTF fonts.
+pub struct UserConfig {
+    pub keys: Vec<(Keycode, Action)>,
+    pub size: (u32, u32),
+    pub theme: Theme,
+    pub ttf: bool,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            keys: keys::KEYS.to_vec(),
+            size: (960, 840),
+            theme: Theme::Light,
+            ttf: false,
+        }
+    }
+}
+
+impl UserConfig {
+    pub fn apply(&self, p: &mut Presentation) {
+        p.config.theme = self.theme;
+        p.config.ttf = self.ttf;
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        Some(d) => PathBuf::from(d),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("eileda").join("config"))
+}
+
+// "<w>x<h>", e.g. "1440x1260".
+pub fn parse_size(s: &str) -> Option<(u32, u32)> {
+    let (w, h) = s.split_once('x')?;
+    match (w.parse(), h.parse()) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Some((w, h)),
+        _ => None,
+    }
+}
+
+// A missing config file is the same as an empty one.
+pub fn load() -> UserConfig {
+    let mut cfg = UserConfig::default();
+    let path = match path() {
+        Some(p) => p,
+        None => return cfg,
+    };
+    let data = match fs::read_to_string(&path) {
+        Ok(d) => d,
+        Err(_) => return cfg,
+    };
+
+    for (i, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let loc = format!("{}:{}", path.display(), i + 1);
+
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match cmd {
+            "bind" => {
+                let (name, action) = rest.rsplit_once(' ').unwrap_or((rest, ""));
+                match (Keycode::from_name(name.trim()), keys::action(action)) {
+                    (Some(k), Some(a)) => {
+                        cfg.keys.retain(|(b, _)| *b != k);
+                        cfg.keys.push((k, a));
+                    }
+                    (None, _) => eprintln!("{}: Error: Unknown key: {}", loc, name),
+                    (_, None) => eprintln!("{}: Error: Unknown action: {}", loc, action),
+                }
+            }
+            "unbind" => match Keycode::from_name(rest) {
+                Some(k) => cfg.keys.retain(|(b, _)| *b != k),
+                None => eprintln!("{}: Error: Unknown key: {}", loc, rest),
+            },
+            "size" => match parse_size(rest) {
+                Some(s) => cfg.size = s,
+                None => eprintln!("{}: Error: Bad window size: {}", loc, rest),
+            },
+            "theme" => match rest {
+                "light" => cfg.theme = Theme::Light,
+                "dark" => cfg.theme = Theme::Dark,
+                t => eprintln!("{}: Error: Unknown theme: {}", loc, t),
+            },
+            "renderer" => match rest {
+                "ufx" => cfg.ttf = false,
+                "ttf" => cfg.ttf = true,
+                r => eprintln!("{}: Error: Unknown renderer: {}", loc, r),
+            },
+            c => eprintln!("{}: Error: Unknown setting: {}", loc, c),
+        }
+    }
+
+    cfg
+}
@@ -0,0 +1,115 @@
+use sdl2::image::{InitFlag, SaveSurface};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use std::fs;
+use std::path::Path;
+
+use crate::cli::Options;
+use eileda::layout::{H, W};
+use eileda::slide::Presentation;
+use crate::config;
+use eileda::md;
+
+// Render every slide to a PNG, at window size, along with an index.html that
+// shows them in order. Hidden slides are left out unless asked for.
+pub fn main(opts: &Options, out: &str) -> i32 {
+    let sdl_context = sdl2::init().unwrap();
+    let _image_context = sdl2::image::init(InitFlag::PNG | InitFlag::JPG).unwrap();
+    let font_context = sdl2::ttf::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+
+    let window = video_subsystem
+        .window("eileda", (W * 2) as u32, (H * 2) as u32)
+        .hidden()
+        .build()
+        .unwrap();
+    let mut canvas = window.into_canvas().target_texture().build().unwrap();
+    let texture_creator = canvas.texture_creator();
+
+    let lexed = md::lex(&opts.file);
+    let mut p = Presentation::load(md::parse(&lexed), &texture_creator, &font_context);
+    config::load().apply(&mut p);
+    p.config.ttf |= opts.ttf;
+    p.config.show_hidden = opts.hidden;
+
+    if p.slides.is_empty() {
+        eprintln!("Presentation is empty");
+        return 1;
+    }
+    p.report_overflow();
+
+    if let Err(e) = fs::create_dir_all(out) {
+        eprintln!("Couldn't create {}: {}", out, e);
+        return 1;
+    }
+
+    let (w, h) = ((W * 2) as u32, (H * 2) as u32);
+    let mut target = texture_creator
+        .create_texture_target(PixelFormatEnum::ARGB8888, w, h)
+        .unwrap();
+
+    let mut pages = Vec::new();
+    for i in (0..p.slides.len()).filter(|&i| p.visible(i)) {
+        let mut pixels = Vec::new();
+        canvas
+            .with_texture_canvas(&mut target, |c| {
+                c.set_scale(2.0, 2.0).unwrap();
+                p.draw(i, c);
+                pixels = c.read_pixels(None, PixelFormatEnum::ARGB8888).unwrap();
+            })
+            .unwrap();
+
+        let name = format!("slide-{:03}.png", i + 1);
+        let path = Path::new(out).join(&name);
+        let saved = Surface::from_data(&mut pixels, w, h, w * 4, PixelFormatEnum::ARGB8888)
+            .and_then(|s| s.save(&path));
+        if let Err(e) = saved {
+            eprintln!("Couldn't save {}: {}", path.display(), e);
+            return 1;
+        }
+        pages.push((name, p.slides[i].title.clone().unwrap_or_default()));
+    }
+
+    let index = Path::new(out).join("index.html");
+    if let Err(e) = fs::write(&index, index_html(&p, &pages)) {
+        eprintln!("Couldn't write {}: {}", index.display(), e);
+        return 1;
+    }
+
+    println!("Exported {} slides to {}", pages.len(), out);
+    0
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn index_html(p: &Presentation, pages: &[(String, String)]) -> String {
+    let title = p.meta.title.clone().unwrap_or("eileda".to_string());
+    let byline = [&p.meta.author, &p.meta.event, &p.meta.date]
+        .into_iter()
+        .flatten()
+        .map(|s| escape(s))
+        .collect::<Vec<_>>()
+        .join(" &middot; ");
+
+    let mut html = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n", escape(&title));
+    for (name, value) in [("author", &p.meta.author), ("date", &p.meta.date), ("event", &p.meta.event)] {
+        if let Some(value) = value {
+            html += &format!("<meta name=\"{}\" content=\"{}\">\n", name, escape(value));
+        }
+    }
+    html += &format!(
+        "<style>body {{ background: #232334; color: #dcdcc8; font-family: sans-serif; text-align: center; }}\n\
+         img {{ display: block; margin: 2em auto; max-width: 100%; }}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape(&title)
+    );
+    if !byline.is_empty() {
+        html += &format!("<p>{}</p>\n", byline);
+    }
+    for (name, alt) in pages {
+        html += &format!("<img src=\"{}\" alt=\"{}\">\n", name, escape(alt));
+    }
+    html += "</body>\n</html>\n";
+    html
+}
@@ -0,0 +1,261 @@
+use sdl2::image::SaveSurface;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::surface::Surface;
+use std::fs;
+use std::path::Path;
+
+use eileda::uf2::{self, Face};
+
+const SHEET_SCALE: usize = 2;
+const SHEET_PAD: usize = 4;
+
+pub fn main(prog: &str, args: &[String]) -> i32 {
+    if args.first().map(|a| a.as_str()) == Some("convert") {
+        if args.len() != 4 {
+            eprintln!("Usage: {} fonts convert <in.ttf> <px> <out.uf2>", prog);
+            return 2;
+        }
+        return match args[2].parse() {
+            Ok(px) => convert(&args[1], px, &args[3]),
+            Err(_) => {
+                eprintln!("Bad pixel size: {}", args[2]);
+                2
+            }
+        };
+    }
+
+    match args.len() {
+        0 => {
+            list();
+            0
+        }
+        2 => specimen(&args[0], &args[1]),
+        3 => sample(&args[0], &args[1], &args[2]),
+        _ => {
+            eprintln!("Usage: {} fonts [name out.png [sample]]", prog);
+            2
+        }
+    }
+}
+
+fn find(name: &str) -> Option<&'static dyn Face> {
+    let name = name.to_uppercase();
+    uf2::FONTS
+        .iter()
+        .find(|(n, _)| *n == name || format!("FONT_{}", n) == name)
+        .map(|(_, f)| *f)
+}
+
+fn list() {
+    println!("{:<12} {:>6} {:>7} {:>4} {:>8}", "name", "height", "sprites", "cell", "coverage");
+    for (name, font) in uf2::FONTS.iter() {
+        println!(
+            "{:<12} {:>6} {:>7} {:>4} {:>4}/256",
+            name.to_lowercase(),
+            font.height(),
+            font.sprites(),
+            font.cell(),
+            font.coverage(),
+        );
+    }
+}
+
+fn blank(w: usize, h: usize) -> Canvas<Surface<'static>> {
+    let surface = Surface::new(w as _, h as _, PixelFormatEnum::RGB888).unwrap();
+    let mut canvas = surface.into_canvas().unwrap();
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas.clear();
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas
+}
+
+fn save(canvas: Canvas<Surface>, out: &str) -> i32 {
+    match canvas.into_surface().save(out) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Couldn't save {}: {}", out, e);
+            1
+        }
+    }
+}
+
+// 16x16 grid of every glyph. Each cell shows the glyph's advance width as a
+// shaded box, and a rule across the cell marks the font's nominal height, so
+// that misaligned or mis-sized glyphs stand out.
+fn specimen(name: &str, out: &str) -> i32 {
+    let font = match find(name) {
+        Some(f) => f,
+        None => {
+            eprintln!("Unknown font: {}", name);
+            return 1;
+        }
+    };
+
+    let sty = uf2::Style::default().scaled(SHEET_SCALE);
+    let cell = font.cell() * SHEET_SCALE + SHEET_PAD * 2;
+    let mut canvas = blank(cell * 16 + 1, cell * 16 + 1);
+
+    for ch in 0..=255u8 {
+        let (cx, cy) = ((ch as usize % 16) * cell, (ch as usize / 16) * cell);
+        let (gx, gy) = (cx + SHEET_PAD, cy + SHEET_PAD);
+
+        canvas.set_draw_color(Color::RGB(220, 220, 200));
+        canvas.draw_rect(Rect::new(cx as _, cy as _, cell as u32 + 1, cell as u32 + 1)).unwrap();
+
+        let w = font.width(ch) * SHEET_SCALE;
+        if w > 0 {
+            let h = font.cell() * SHEET_SCALE;
+            canvas.set_draw_color(Color::RGB(186, 187, 186));
+            canvas.fill_rect(Rect::new(gx as _, gy as _, w as _, h as _)).unwrap();
+        }
+
+        canvas.set_draw_color(Color::RGB(0xbb, 0x33, 0x33));
+        let by = gy + font.height() * SHEET_SCALE;
+        canvas.fill_rect(Rect::new(cx as _, by as _, cell as _, 1)).unwrap();
+
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        uf2::draw_char(&mut canvas, font, &sty, gx, gy, ch);
+    }
+
+    save(canvas, out)
+}
+
+fn sample(name: &str, out: &str, text: &str) -> i32 {
+    let font = match find(name) {
+        Some(f) => f,
+        None => {
+            eprintln!("Unknown font: {}", name);
+            return 1;
+        }
+    };
+
+    let sty = uf2::Style::default().scaled(SHEET_SCALE);
+    let w = uf2::measure(font, &sty, text) + SHEET_PAD * 2;
+    let h = sty.line_height(font) + SHEET_PAD * 2;
+    let mut canvas = blank(w, h);
+
+    let b = uf2::Bounds { lx: SHEET_PAD, ex: w + 1, x: SHEET_PAD, y: SHEET_PAD };
+    uf2::draw(&mut canvas, font, &sty, b, text);
+
+    save(canvas, out)
+}
+
+// Alpha at or above which a rasterized pixel is considered set.
+const THRESHOLD: u8 = 128;
+
+// A font rasterized at runtime, in the same shape as a parsed Ufx.
+struct Raster {
+    height: usize,
+    cell: usize,
+    widths: [u8; 256],
+    pixels: Vec<bool>,
+}
+
+impl Face for Raster {
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn sprites(&self) -> usize {
+        (self.cell / 8) * (self.cell / 8)
+    }
+
+    fn width(&self, ch: u8) -> usize {
+        self.widths[ch as usize] as usize
+    }
+
+    fn pixel(&self, ch: u8, x: usize, y: usize) -> bool {
+        if x >= self.cell || y >= self.cell {
+            return false;
+        }
+        self.pixels[(ch as usize * self.cell + y) * self.cell + x]
+    }
+}
+
+fn rasterize(path: &str, px: u16) -> Result<Raster, String> {
+    let ttf = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let font = ttf.load_font(path, px)?;
+
+    let height = font.height().max(1) as usize;
+    let cell = height.div_ceil(8) * 8;
+    let mut r = Raster {
+        height,
+        cell,
+        widths: [0; 256],
+        pixels: vec![false; 256 * cell * cell],
+    };
+
+    // Glyphs are mapped by Latin-1 codepoint, which is what the text drawing
+    // code indexes with.
+    for ch in 0..=255u8 {
+        let c = ch as char;
+        if c.is_control() || font.find_glyph(c).is_none() {
+            continue;
+        }
+
+        let surf = font
+            .render_char(c)
+            .blended(Color::RGB(0, 0, 0))
+            .map_err(|e| e.to_string())
+            .and_then(|s| s.convert_format(PixelFormatEnum::ARGB8888))
+            .map_err(|e| format!("Couldn't render {:?}: {}", c, e))?;
+
+        let (w, h) = (surf.width() as usize, surf.height() as usize);
+        let pitch = surf.pitch() as usize;
+        r.widths[ch as usize] = w.min(cell) as u8;
+
+        surf.with_lock(|data| {
+            for y in 0..h.min(cell) {
+                for x in 0..w.min(cell) {
+                    // ARGB8888 is stored as B, G, R, A on little-endian.
+                    let a = data[y * pitch + x * 4 + 3];
+                    r.pixels[(ch as usize * cell + y) * cell + x] = a >= THRESHOLD;
+                }
+            }
+        });
+    }
+
+    Ok(r)
+}
+
+fn convert(input: &str, px: u16, out: &str) -> i32 {
+    let raster = match rasterize(input, px) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Couldn't convert {}: {}", input, e);
+            return 1;
+        }
+    };
+
+    let ext = format!("uf{}", raster.cell / 8);
+    if Path::new(out).extension().and_then(|e| e.to_str()) != Some(&ext) {
+        eprintln!("Warning: {}px glyphs are {} files, not {}", px, ext, out);
+    }
+
+    if let Err(e) = fs::write(out, uf2::serialize(&raster)) {
+        eprintln!("Couldn't write {}: {}", out, e);
+        return 1;
+    }
+
+    let sty = uf2::Style::default().scaled(SHEET_SCALE);
+    let text = "The quick brown fox jumps over the lazy dog. 0123456789";
+    let w = uf2::measure(&raster, &sty, text) + SHEET_PAD * 2;
+    let h = sty.line_height(&raster) + SHEET_PAD * 2;
+    let mut canvas = blank(w, h);
+    let b = uf2::Bounds { lx: SHEET_PAD, ex: w + 1, x: SHEET_PAD, y: SHEET_PAD };
+    uf2::draw(&mut canvas, &raster, &sty, b, text);
+    if save(canvas, &format!("{}.png", out)) != 0 {
+        return 1;
+    }
+
+    println!(
+        "Wrote {}. Register it with:\n    NAME := @sz {:<2} @h {} \"{}\",",
+        out,
+        raster.sprites(),
+        raster.height,
+        out,
+    );
+    0
+}
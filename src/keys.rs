@@ -0,0 +1,104 @@
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    Next,
+    Prev,
+    First,
+    Last,
+    // Jump to the slide whose number was typed just before.
+    Goto,
+    Reload,
+    ToggleTtf,
+    Overview,
+    Quit,
+    Fullscreen,
+    Blackout,
+    Whiteout,
+    Search,
+}
+
+// Names used in the config file.
+pub const ACTIONS: &[(&str, Action)] = &[
+    ("next", Action::Next),
+    ("prev", Action::Prev),
+    ("first", Action::First),
+    ("last", Action::Last),
+    ("goto", Action::Goto),
+    ("reload", Action::Reload),
+    ("toggle-ttf", Action::ToggleTtf),
+    ("overview", Action::Overview),
+    ("quit", Action::Quit),
+    ("fullscreen", Action::Fullscreen),
+    ("blackout", Action::Blackout),
+    ("whiteout", Action::Whiteout),
+    ("search", Action::Search),
+];
+
+// Several keys may map to the same action. Presentation clickers usually send
+// PageUp/PageDown.
+pub const KEYS: &[(Keycode, Action)] = &[
+    (Keycode::Right, Action::Next),
+    (Keycode::Down, Action::Next),
+    (Keycode::Space, Action::Next),
+    (Keycode::PageDown, Action::Next),
+    (Keycode::Left, Action::Prev),
+    (Keycode::Up, Action::Prev),
+    (Keycode::PageUp, Action::Prev),
+    (Keycode::Backspace, Action::Prev),
+    (Keycode::Home, Action::First),
+    (Keycode::End, Action::Last),
+    (Keycode::Return, Action::Goto),
+    (Keycode::KpEnter, Action::Goto),
+    (Keycode::R, Action::Reload),
+    (Keycode::T, Action::ToggleTtf),
+    (Keycode::O, Action::Overview),
+    (Keycode::Tab, Action::Overview),
+    (Keycode::Escape, Action::Quit),
+    (Keycode::F, Action::Fullscreen),
+    (Keycode::F11, Action::Fullscreen),
+    (Keycode::B, Action::Blackout),
+    (Keycode::W, Action::Whiteout),
+    (Keycode::Slash, Action::Search),
+];
+
+pub const BUTTONS: &[(MouseButton, Action)] = &[
+    (MouseButton::Left, Action::Next),
+    (MouseButton::Right, Action::Prev),
+];
+
+pub fn action(name: &str) -> Option<Action> {
+    ACTIONS.iter().find(|(n, _)| *n == name).map(|(_, a)| *a)
+}
+
+pub fn key(bindings: &[(Keycode, Action)], k: Keycode) -> Option<Action> {
+    bindings.iter().find(|(b, _)| *b == k).map(|(_, a)| *a)
+}
+
+pub fn button(bindings: &[(MouseButton, Action)], b: MouseButton) -> Option<Action> {
+    bindings.iter().find(|(m, _)| *m == b).map(|(_, a)| *a)
+}
+
+// Scrolling down moves forward.
+pub fn wheel(y: i32) -> Option<Action> {
+    match y {
+        y if y < 0 => Some(Action::Next),
+        y if y > 0 => Some(Action::Prev),
+        _ => None,
+    }
+}
+
+// Value of a digit key, from either the number row or the keypad.
+pub fn digit(k: Keycode) -> Option<usize> {
+    let n = k.into_i32();
+    if (Keycode::Num0.into_i32()..=Keycode::Num9.into_i32()).contains(&n) {
+        Some((n - Keycode::Num0.into_i32()) as usize)
+    } else if (Keycode::Kp1.into_i32()..=Keycode::Kp9.into_i32()).contains(&n) {
+        Some((n - Keycode::Kp1.into_i32()) as usize + 1)
+    } else if k == Keycode::Kp0 {
+        Some(0)
+    } else {
+        None
+    }
+}
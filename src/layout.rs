@@ -0,0 +1,1133 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::slide::*;
+use crate::uf2::{self, Bounds};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Texture;
+
+const PAR_PAD: usize = 12;
+const MIN_SCALE: f32 = 0.6;
+const LST_MAR: usize = 8;
+const IMG_SPC: usize = 12;
+const COL_SPC: usize = 12;
+
+pub const W: usize = 960 / 2;
+pub const H: usize = 840 / 2;
+
+pub struct Palette {
+    pub bg: Color,
+    pub fg: Color,
+    pub head_bg: Color,
+    pub head_fg: Color,
+    pub quote_bar: Color,
+}
+
+const LIGHT: Palette = Palette {
+    bg: Color::RGB(255, 255, 255),
+    fg: Color::RGB(0, 0, 0),
+    head_bg: Color::RGB(0x23, 0x23, 0x34),
+    head_fg: Color::RGB(220, 220, 200),
+    quote_bar: Color::RGB(0xba, 0xbb, 0xba),
+};
+
+const DARK: Palette = Palette {
+    bg: Color::RGB(0x23, 0x23, 0x34),
+    fg: Color::RGB(220, 220, 200),
+    head_bg: Color::RGB(220, 220, 200),
+    head_fg: Color::RGB(0x23, 0x23, 0x34),
+    quote_bar: Color::RGB(0x5a, 0x5b, 0x6a),
+};
+
+// A positioned box. Slides are laid out into a tree of these, which is then
+// painted front-to-back; nothing in here touches a canvas.
+pub struct Node<'p, 'a> {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+    pub kind: Kind<'p, 'a>,
+    pub children: Vec<Node<'p, 'a>>,
+}
+
+pub enum Kind<'p, 'a> {
+    Group,
+    Fill(Color),
+    Text { fl: DrawFl, text: String, color: Color, scale: f32 },
+    Glyph { font: &'static dyn uf2::Face, ch: u8, color: Color },
+    // Images sized by the metrics but without a texture aren't painted.
+    Image { texture: Option<&'p Texture<'a>>, src: Option<Rect> },
+}
+
+impl<'p, 'a> Node<'p, 'a> {
+    pub fn leaf(x: usize, y: usize, w: usize, h: usize, kind: Kind<'p, 'a>) -> Self {
+        Node { x, y, w, h, kind, children: Vec::new() }
+    }
+
+    // Group spanning all of its children.
+    pub fn group(children: Vec<Node<'p, 'a>>) -> Self {
+        let x = children.iter().map(|c| c.x).min().unwrap_or(0);
+        let y = children.iter().map(|c| c.y).min().unwrap_or(0);
+        let ex = children.iter().map(|c| c.x + c.w).max().unwrap_or(x);
+        let ey = children.iter().map(|c| c.y + c.h).max().unwrap_or(y);
+        Node { x, y, w: ex - x, h: ey - y, kind: Kind::Group, children }
+    }
+
+    pub fn shift(&mut self, dx: usize, dy: usize) {
+        self.x += dx;
+        self.y += dy;
+        for c in self.children.iter_mut() {
+            c.shift(dx, dy);
+        }
+    }
+}
+
+impl<'a> Presentation<'a> {
+    pub fn layout<'p>(&'p self, slide: usize) -> Node<'p, 'a> {
+        Layout::new(self).fitted(slide).0
+    }
+
+    // How far the slide's content runs past the bottom of the slide, after
+    // any fitting.
+    pub fn overflow(&self, slide: usize) -> usize {
+        Layout::new(self).fitted(slide).1
+    }
+
+    pub fn report_overflow(&self) {
+        for i in 0..self.slides.len() {
+            let over = self.overflow(i);
+            if over > 0 {
+                eprintln!(
+                    "Warning: Slide {} ({}) overflows by {}px",
+                    i + 1,
+                    self.slides[i].title.as_deref().unwrap_or("untitled"),
+                    over,
+                );
+            }
+        }
+    }
+}
+
+// Spacing and text size to lay a slide out with. These are relaxed step by
+// step when a slide doesn't fit, if it asks for that with .FIT.
+#[derive(Copy, Clone, Debug)]
+pub struct Fit {
+    pub pad: usize,
+    pub scale: f32,
+}
+
+impl Default for Fit {
+    fn default() -> Self {
+        Self { pad: PAR_PAD, scale: 1.0 }
+    }
+}
+
+impl Fit {
+    // Bitmap fonts only come in their native size, so text only shrinks in
+    // TTF mode.
+    fn step(self, mode: FitMode, ttf: bool) -> Option<Fit> {
+        match mode {
+            FitMode::Off => None,
+            _ if self.pad > 0 => Some(Fit { pad: self.pad.saturating_sub(2), ..self }),
+            FitMode::Shrink if ttf && self.scale > MIN_SCALE => {
+                Some(Fit { scale: self.scale - 0.1, ..self })
+            }
+            _ => None,
+        }
+    }
+}
+
+// Sizes of text, before any scaling by the layout, and of images. Presentation
+// measures with whichever fonts are in use and its loaded images; UfxMetrics
+// only knows the bitmap fonts and no images, but needs no window.
+pub trait Metrics {
+    fn measure(&self, fl: DrawFl, text: &str) -> usize;
+    fn line_height(&self, fl: DrawFl) -> usize;
+
+    // Width and height of an image, if it is loaded.
+    fn image_size(&self, _path: &Path) -> Option<(u32, u32)> {
+        None
+    }
+}
+
+pub struct UfxMetrics {
+    pub tracking: usize,
+}
+
+impl Metrics for UfxMetrics {
+    fn measure(&self, fl: DrawFl, text: &str) -> usize {
+        let (fnt, sty) = ufx_font(fl, self.tracking);
+        uf2::measure(fnt, &sty, text)
+    }
+
+    fn line_height(&self, fl: DrawFl) -> usize {
+        let (fnt, sty) = ufx_font(fl, self.tracking);
+        sty.line_height(fnt)
+    }
+}
+
+// Everything a slide is laid out with: the deck, its images and font
+// metrics, the fit, the default alignment of its text, and an integer zoom
+// applied to all text on top of the fit.
+#[derive(Copy, Clone)]
+pub struct Layout<'p, 'a> {
+    pub config: &'p GlobalConfig,
+    pub meta: &'p Meta,
+    pub slides: &'p [Slide],
+    pub images: &'p HashMap<PathBuf, Texture<'a>>,
+    pub metrics: &'p dyn Metrics,
+    pub fit: Fit,
+    pub align: Align,
+    pub zoom: usize,
+}
+
+impl<'p, 'a> Layout<'p, 'a> {
+    pub fn new(p: &'p Presentation<'a>) -> Self {
+        Layout {
+            config: &p.config,
+            meta: &p.meta,
+            slides: &p.slides,
+            images: &p.images,
+            metrics: p,
+            fit: Fit::default(),
+            align: p.config.align,
+            zoom: 1,
+        }
+    }
+
+    // A deck that isn't loaded for a window. Images are sized by the
+    // metrics, and left out if it doesn't know them.
+    pub fn for_deck(deck: &'p Deck, images: &'p HashMap<PathBuf, Texture<'a>>, metrics: &'p dyn Metrics) -> Self {
+        Layout {
+            config: &deck.config,
+            meta: &deck.meta,
+            slides: &deck.slides,
+            images,
+            metrics,
+            fit: Fit::default(),
+            align: deck.config.align,
+            zoom: 1,
+        }
+    }
+
+    // Lay a slide out, relaxing the fit step by step while it overflows, if
+    // it asks for that with .FIT. Returns the tree and the overflow.
+    pub fn fitted(&self, slide: usize) -> (Node<'p, 'a>, usize) {
+        let mode = self.slides[slide].fit.unwrap_or(self.config.fit);
+        let mut l = *self;
+
+        loop {
+            let (tree, over) = l.slide(slide);
+            match l.fit.step(mode, self.config.ttf) {
+                Some(next) if over > 0 => l.fit = next,
+                _ => return (tree, over),
+            }
+        }
+    }
+
+    // Palette of the deck's theme, which slides are painted with.
+    pub fn colors(&self) -> &'static Palette {
+        match self.config.theme {
+            Theme::Light => &LIGHT,
+            Theme::Dark => &DARK,
+        }
+    }
+
+    // Scale of text relative to its font's size. Always integral for bitmap
+    // fonts.
+    pub fn text_scale(&self) -> f32 {
+        if self.config.ttf {
+            self.fit.scale * self.zoom as f32
+        } else {
+            self.zoom as f32
+        }
+    }
+
+    pub fn measure(&self, fl: DrawFl, value: &str) -> usize {
+        (self.metrics.measure(fl, value) as f32 * self.text_scale()) as usize
+    }
+
+    pub fn line_height(&self, fl: DrawFl) -> usize {
+        (self.metrics.line_height(fl) as f32 * self.text_scale()) as usize
+    }
+
+    pub fn slide(&self, slide: usize) -> (Node<'p, 'a>, usize) {
+        let mut nodes = vec![Node::leaf(0, 0, W, H, Kind::Fill(self.colors().bg))];
+
+        let mut lx = 0;
+        let mut ex = W;
+        let mut sy = 0;
+        let mut ey = H;
+
+        let margin = self.config.margin.as_ref().and_then(|m| Some((m, self.metrics.image_size(&m.image)?)));
+        if let Some((margin, (iw, ih))) = margin {
+            let image = self.images.get(&margin.image);
+            let x_pad = (ex - lx).saturating_sub(margin.middle / 2) / 2;
+            ex -= x_pad;
+            lx += x_pad;
+
+            let (lmw, lmh) = (lx as u32, H as u32); // left margin width/height
+            nodes.push(Node::leaf(0, 0, lx, H, Kind::Image {
+                texture: image,
+                src: Some(Rect::new(0, 0, lmw.min(iw), lmh.min(ih))),
+            }));
+
+            let (rmw, rmh) = ((W - ex) as u32, H as u32); // right margin width/height
+            let img_start = iw.saturating_sub(rmw) as i32;
+            nodes.push(Node::leaf(ex, 0, W - ex, H, Kind::Image {
+                texture: image,
+                src: Some(Rect::new(img_start, 0, rmw.min(iw), rmh.min(ih))),
+            }));
+        }
+
+        if self.config.padding > 0 {
+            lx += self.config.padding;
+            ex -= self.config.padding;
+            sy += self.config.padding;
+            ey -= self.config.padding;
+        }
+
+        let content = &self.slides[slide].content;
+        let layout = self.slides[slide].layout.unwrap_or_default();
+        let title = match layout {
+            SlideLayout::BigStatement => None,
+            SlideLayout::Title => self.slides[slide].title.as_ref().or(self.meta.title.as_ref()),
+            _ => self.slides[slide].title.as_ref(),
+        };
+
+        // Title slides center their text unless told otherwise, big statements
+        // always do. Section slides are only centered vertically.
+        let l = match layout {
+            SlideLayout::Title => Layout { align: Align::Center, ..*self },
+            SlideLayout::BigStatement => Layout { align: Align::Center, zoom: 2, ..*self },
+            _ => *self,
+        };
+
+        // An alignment given before any content also applies to the title.
+        let talign = match content.first() {
+            Some(Content::Align(a)) => *a,
+            _ => l.align,
+        };
+
+        let over;
+        match layout {
+            SlideLayout::Content | SlideLayout::TwoColumn => {
+                if let Some(title) = title {
+                    let spans = [(DrawFl::TITLE, title.clone())];
+                    let (runs, _, dy) = layout_text(&l, Bounds { lx, ex, x: lx, y: sy }, talign, &spans);
+                    nodes.push(Node::group(runs));
+                    sy += dy + (l.fit.pad * 3);
+                }
+
+                let mut body = Vec::new();
+                layout_content(&l, &mut body, content, lx, ex, sy, ey);
+                let body = Node::group(body);
+                over = (body.y + body.h).saturating_sub(ey);
+                nodes.push(body);
+            }
+            SlideLayout::Title | SlideLayout::Section | SlideLayout::BigStatement => {
+                let mut block = Vec::new();
+                let mut y = sy;
+
+                if let Some(title) = title {
+                    let spans = [(DrawFl::TITLE, title.clone())];
+                    let (runs, _, _) = layout_text(&l, Bounds { lx, ex, x: lx, y }, talign, &spans);
+                    let t = Node::group(runs);
+                    y = t.y + t.h + l.fit.pad;
+                    block.push(t);
+
+                    if layout == SlideLayout::Section {
+                        block.push(Node::leaf(lx, y, ex - lx, 2, Kind::Fill(self.colors().fg)));
+                        y += 2;
+                    }
+                    y += l.fit.pad;
+                }
+
+                let mut body = Vec::new();
+                layout_content(&l, &mut body, content, lx, ex, y, ey);
+
+                // A title slide without content of its own gets a byline
+                // from the deck's metadata.
+                let blank = content.iter().all(|c| matches!(c, Content::Md(m) if crate::md::is_blank(m)));
+                if layout == SlideLayout::Title && blank {
+                    let m = &self.meta;
+                    for line in [&m.author, &m.event, &m.date].into_iter().flatten() {
+                        let (runs, _, _) = layout_text(&l, Bounds { lx, ex, x: lx, y }, l.align, &[(DrawFl::NONE, line.clone())]);
+                        let line = Node::group(runs);
+                        y = line.y + line.h + l.fit.pad / 2;
+                        body.push(line);
+                    }
+                }
+                block.push(Node::group(body));
+
+                let mut block = Node::group(block);
+                let (top, height) = (block.y.min(sy), block.y + block.h - block.y.min(sy));
+                over = (top + height).saturating_sub(ey);
+                block.shift(0, (ey - sy).saturating_sub(height) / 2);
+                nodes.push(block);
+            }
+            SlideLayout::ImageFull => {
+                let (images, rest): (Vec<_>, Vec<_>) =
+                    content.iter().partition(|c| matches!(c, Content::Img(_)));
+
+                for img in images {
+                    if let Content::Img(path) = img {
+                        let Some((iw, ih)) = self.metrics.image_size(path) else {
+                            continue;
+                        };
+                        let (iw, ih) = (iw as f32, ih as f32);
+                        let k = (W as f32 / iw).min(H as f32 / ih);
+                        let (cw, ch) = ((iw * k) as usize, (ih * k) as usize);
+                        nodes.push(Node::leaf((W - cw) / 2, (H - ch) / 2, cw, ch, Kind::Image {
+                            texture: self.images.get(path),
+                            src: None,
+                        }));
+                    }
+                }
+
+                let mut body = Vec::new();
+                layout_content(&l, &mut body, rest, lx, ex, sy, ey);
+                let body = Node::group(body);
+                let bottom = if body.children.is_empty() { 0 } else { body.y + body.h };
+                nodes.push(body);
+
+                // Title as a caption along the bottom edge.
+                let mut cap_y = ey;
+                if let Some(title) = title {
+                    let spans = [(DrawFl::TITLE, title.clone())];
+                    let (mut runs, _, _) = layout_text(&l, Bounds { lx, ex, x: lx, y: 0 }, talign, &spans);
+                    for r in runs.iter_mut() {
+                        if let Kind::Text { ref mut color, .. } = r.kind {
+                            *color = self.colors().head_fg;
+                        }
+                    }
+                    let mut cap = Node::group(runs);
+                    let band = cap.h + l.fit.pad * 2;
+                    cap_y = H - band;
+                    cap.shift(0, cap_y + l.fit.pad);
+                    nodes.push(Node::leaf(0, cap_y, W, band, Kind::Fill(self.colors().head_bg)));
+                    nodes.push(cap);
+                }
+
+                over = bottom.saturating_sub(ey.min(cap_y));
+            }
+        }
+
+        // Header and footer go in the padding, above and below the content.
+        let pad = self.config.padding;
+        let lh = self.line_height(DrawFl::NONE);
+        let bars = [
+            (&self.config.header, self.slides[slide].header, pad.saturating_sub(lh) / 2),
+            (&self.config.footer, self.slides[slide].footer, H - pad.max(lh) + pad.saturating_sub(lh) / 2),
+        ];
+        for (segments, shown, y) in bars {
+            if let (Some(segments), true) = (segments, shown) {
+                let mut bar = Vec::new();
+                for (seg, align) in segments.iter().zip([Align::Left, Align::Center, Align::Right]) {
+                    let text = expand_vars(self, slide, seg);
+                    if !text.is_empty() {
+                        let (runs, _, _) = layout_text(self, Bounds { lx, ex, x: lx, y }, align, &[(DrawFl::NONE, text)]);
+                        bar.extend(runs);
+                    }
+                }
+                nodes.push(Node::group(bar));
+            }
+        }
+
+        let mut root = Node::group(nodes);
+        (root.x, root.y, root.w, root.h) = (0, 0, W, H);
+        (root, over)
+    }
+}
+
+// %n: slide number, %N: number of slides, %t: slide title, %d: date (.DTE,
+// or today's), %T: deck title, %a: author, %e: event. Hidden slides aren't
+// counted unless they are shown.
+fn expand_vars(l: &Layout, slide: usize, s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    let counted = |slides: &[Slide]| slides.iter().filter(|s| l.config.show_hidden || !s.hidden).count();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out += &(counted(&l.slides[..slide]) + 1).to_string(),
+            Some('N') => out += &counted(l.slides).to_string(),
+            Some('t') => out += l.slides[slide].title.as_deref().unwrap_or(""),
+            Some('d') => out += &l.meta.date.clone().unwrap_or_else(today),
+            Some('T') => out += l.meta.title.as_deref().unwrap_or(""),
+            Some('a') => out += l.meta.author.as_deref().unwrap_or(""),
+            Some('e') => out += l.meta.event.as_deref().unwrap_or(""),
+            Some(c) => out.push(c),
+            None => out.push('%'),
+        }
+    }
+
+    out
+}
+
+// Today's (UTC) date as YYYY-MM-DD.
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+fn layout_content<'p, 'a>(
+    l: &Layout<'p, 'a>,
+    out: &mut Vec<Node<'p, 'a>>,
+    content: impl IntoIterator<Item = &'p Content>,
+    lx: usize,
+    ex: usize,
+    sy: usize,
+    ey: usize,
+) -> usize {
+    let mut y = sy;
+    let mut align = l.align;
+
+    for item in content {
+        match item {
+            Content::Align(a) => align = *a,
+            Content::Grid(Grid {
+                ratio,
+                first,
+                second,
+                ..
+            }) => {
+                let bx = lx + ((ex - lx) * ratio / 100);
+                let (mut c1, mut c2) = (Vec::new(), Vec::new());
+                let y1 = layout_content(l, &mut c1, first, lx, bx, y, ey);
+                let y2 = layout_content(l, &mut c2, second, bx + COL_SPC, ex, y, ey);
+                out.push(Node::group(vec![Node::group(c1), Node::group(c2)]));
+                y = y1.max(y2);
+            }
+            Content::Img(path) => {
+                // image w/h
+                let Some((iw, ih)) = l.metrics.image_size(path) else {
+                    continue;
+                };
+                let (mw, mh) = ((ex - lx) as _, ey.saturating_sub(y) as _); // max w/h
+                let (cw, ch); // calculated w/h
+
+                // Scale image down into boundaries, depending on which dimension
+                // (height/width) is over bounds most
+                if iw < mw && ih < mh {
+                    cw = iw;
+                    ch = ih;
+                } else if iw.saturating_sub(mw) > ih.saturating_sub(mh) {
+                    cw = mw;
+                    ch = (ih as f32 * (mw as f32 / iw as f32)) as u32;
+                } else {
+                    ch = mh;
+                    cw = (iw as f32 * (mh as f32 / ih as f32)) as u32;
+                }
+
+                let texture = l.images.get(path);
+                out.push(Node::leaf(lx, y, cw as _, ch as _, Kind::Image { texture, src: None }));
+
+                y += ch as usize + IMG_SPC;
+            }
+            Content::Md(md) => {
+                let mut nodes = Vec::new();
+                let (_, ny) = layout_md(l, &mut nodes, md, Bounds { lx, ex, x: lx, y }, DrawFl::NONE, align);
+                out.push(Node::group(nodes));
+                y = ny;
+            }
+        }
+    }
+
+    y
+}
+
+fn layout_md(
+    l: &Layout,
+    out: &mut Vec<Node<'static, 'static>>,
+    node: &markdown::mdast::Node,
+    b: Bounds,
+    fl: DrawFl,
+    align: Align,
+) -> (usize, usize) {
+    use markdown::mdast::Node as N;
+    use markdown::mdast::{BlockQuote, Emphasis, Heading, List, ListItem, Paragraph, Root, Strong, Text};
+
+    let (lx, ex, sx, sy) = (b.lx, b.ex, b.x, b.y);
+    let mut x = sx;
+    let mut y = sy;
+
+    let frect = |out: &mut Vec<Node>, x: usize, y: usize, w: usize, h: usize, c: Color| {
+        out.push(Node::leaf(x, y, w, h, Kind::Fill(c)));
+    };
+
+    match node {
+        N::Root(Root { children, .. }) => {
+            for c in children {
+                let (nx, ny) = layout_md(l, out, c, Bounds { lx, ex, x, y }, fl, align);
+                x = nx;
+                y = ny;
+            }
+            x = sx;
+        }
+        N::Paragraph(Paragraph { children, .. }) => {
+            let mut spans = Vec::new();
+            collect_spans(children, fl, &mut spans);
+            let (runs, _, ny) = layout_text(l, Bounds { lx, ex, x, y }, align, &spans);
+            out.push(Node::group(runs));
+            y = ny;
+
+            x = sx;
+            y += l.line_height(fl);
+            y += l.fit.pad;
+        }
+        N::Heading(Heading {
+            children, depth: _, ..
+        }) => {
+            //y += PAR_PAD;
+
+            // STYLE 2
+            //const HEAD_HR_W: usize = 24;
+            //rect(out, x, y + 6, HEAD_HR_W, 3);
+            //x += HEAD_HR_W + 8;
+
+            // STYLE 1
+            //const HEAD_BOX_PAD_X: usize = 10;
+            //const HEAD_BOX_PAD_Y: usize = 8;
+            let (ox, oy) = (x, y);
+            // x += HEAD_BOX_PAD_X;
+            // y += HEAD_BOX_PAD_Y;
+
+            // STYLE 3
+            // for p in 0..10 {
+            //     frect(out, x, oy, 2, 16);
+            //     x += 6 - 6usize.saturating_sub(p / 1).max(1);
+            // }
+            // x += 6;
+
+            let mut spans = Vec::new();
+            collect_spans(children, fl | DrawFl::HEAD, &mut spans);
+            let (mut runs, _, ny) = layout_text(l, Bounds { lx, ex, x, y }, align, &spans);
+            for r in runs.iter_mut() {
+                if let Kind::Text { ref mut color, .. } = r.kind {
+                    *color = l.colors().head_fg;
+                }
+            }
+            let text = Node::group(runs);
+            let (bx, bex) = if text.children.is_empty() { (ox, ox) } else { (text.x, text.x + text.w) };
+
+            // STYLE 4
+            let lh = l.line_height(fl | DrawFl::HEAD);
+            frect(out, bx - 4, oy - 4, bex - bx + 8, ny - oy + lh + 8, l.colors().head_bg);
+            frect(out, ox - 4, ny + lh + 4, ex - ox, 4, l.colors().head_bg);
+            out.push(text);
+            y = ny;
+
+            // STYLE 3
+            // x += 6;
+            // for p in 0..10 {
+            //     frect(out, x, oy, 2, 16);
+            //     x += 6usize.saturating_sub(p / 2).max(1);
+            // }
+
+            // STYLE 2
+            //y += PAR_PAD;
+            //drect(out, ox, oy, (x - ox) + (HEAD_BOX_PAD_X ), (y - oy) + (HEAD_BOX_PAD_Y * 1));
+
+            // STYLE 1
+            //rect(out, x + 8, y + 6, ex - x, 3);
+
+            x = sx;
+            y += lh;
+            y += l.fit.pad;
+            y += l.fit.pad / 2;
+        }
+        N::Strong(Strong { children, .. }) => {
+            for c in children {
+                let (nx, ny) = layout_md(l, out, c, Bounds { lx, ex, x, y }, fl | DrawFl::BOLD, align);
+                x = nx;
+                y = ny;
+            }
+        }
+        N::Emphasis(Emphasis { children, .. }) => {
+            for c in children {
+                let (nx, ny) = layout_md(l, out, c, Bounds { lx, ex, x, y }, fl | DrawFl::EMPH, align);
+                x = nx;
+                y = ny;
+            }
+        }
+        N::List(List {
+            children,
+            ordered,
+            start,
+            spread: _,
+            ..
+        }) => {
+            let mut items = Vec::new();
+            for (i, c) in children.iter().enumerate() {
+                let mut item = Vec::new();
+                let o;
+                if !*ordered {
+                    if l.config.ttf {
+                        let lbl = "• ";
+                        let (runs, _, _) = layout_text(l, Bounds { lx: lx + LST_MAR, ex, x: x + LST_MAR, y }, Align::Left, &[(DrawFl::NONE, lbl.to_string())]);
+                        item.extend(runs);
+                        o = LST_MAR + l.measure(DrawFl::NONE, lbl);
+                    } else {
+                        let (bfnt, bsty) = (&*uf2::FONT_NEWYORK14, uf2::Style::default());
+                        let bw = bsty.advance(bfnt, 0xA5);
+                        item.push(Node::leaf(x + LST_MAR, y, bw, bsty.line_height(bfnt), Kind::Glyph {
+                            font: bfnt,
+                            ch: 0xA5,
+                            color: l.colors().fg,
+                        }));
+                        o = LST_MAR + bw + bsty.advance(bfnt, b' ');
+                    }
+                } else {
+                    let lbl = format!("{}) ", start.unwrap_or(1) as usize + i);
+                    o = LST_MAR + l.measure(DrawFl::NONE, &lbl);
+                    let (runs, _, _) = layout_text(l, Bounds { lx: lx + LST_MAR, ex, x: x + LST_MAR, y }, Align::Left, &[(DrawFl::NONE, lbl)]);
+                    item.extend(runs);
+                }
+                let (nx, ny) = layout_md(l, &mut item, c, Bounds { lx: lx + o, ex, x: x + o, y }, fl, align);
+                items.push(Node::group(item));
+                x = nx - o;
+                y = ny;
+            }
+            out.push(Node::group(items));
+            x = sx;
+            y += l.fit.pad;
+        }
+        N::ListItem(ListItem { children, .. }) => {
+            for c in children {
+                let (nx, ny) = layout_md(l, out, c, Bounds { lx, ex, x, y }, fl, align);
+                x = nx;
+                y = ny - l.fit.pad;
+            }
+        }
+        N::BlockQuote(BlockQuote { children, .. }) => {
+            let oldy = sy;
+            let mut quote = Vec::new();
+            for c in children {
+                let (nx, ny) = layout_md(l, &mut quote, c, Bounds { lx: lx + 10, ex, x: x + 10, y }, fl, align);
+                x = nx;
+                y = ny;
+            }
+            x = lx;
+            frect(&mut quote, lx, oldy, 4, y - oldy - l.fit.pad, l.colors().quote_bar);
+            out.push(Node::group(quote));
+        }
+        N::Text(Text { value, .. }) => {
+            let (runs, nx, ny) = layout_text(l, Bounds { lx, ex, x, y }, align, &[(fl, value.clone())]);
+            out.extend(runs);
+            x = nx;
+            y = ny;
+        }
+        // Anything else is left out; check reports it as unsupported.
+        _ => {}
+    }
+
+    (x, y)
+}
+
+// Flatten inline markdown into styled spans, to be laid out as a block.
+fn collect_spans(children: &[markdown::mdast::Node], fl: DrawFl, spans: &mut Vec<(DrawFl, String)>) {
+    use markdown::mdast::Node as N;
+    use markdown::mdast::*;
+
+    for c in children {
+        match c {
+            N::Text(Text { value, .. }) => spans.push((fl, value.clone())),
+            N::Strong(Strong { children, .. }) => collect_spans(children, fl | DrawFl::BOLD, spans),
+            N::Emphasis(Emphasis { children, .. }) => collect_spans(children, fl | DrawFl::EMPH, spans),
+            // Left out; `eileda check` reports them.
+            _ => {}
+        }
+    }
+}
+
+// Soft hyphen: an invisible break opportunity, shown as '-' when broken at.
+const SHY: char = '\u{ad}';
+
+// Unbreakable (except by hyphenation) sequence of pieces, possibly in
+// different styles, with the width of its trailing whitespace, if any.
+struct Word {
+    pieces: Vec<(DrawFl, String, usize)>,
+    width: usize,
+    space: usize,
+}
+
+impl Word {
+    fn new(l: &Layout, pieces: Vec<(DrawFl, String)>) -> Word {
+        let pieces = pieces
+            .into_iter()
+            .filter(|(_, t)| !t.is_empty())
+            .map(|(fl, t)| {
+                let w = l.measure(fl, &t.replace(SHY, ""));
+                (fl, t, w)
+            })
+            .collect::<Vec<_>>();
+        let width = pieces.iter().map(|(_, _, w)| w).sum();
+        let space = match pieces.last() {
+            Some((fl, t, w)) if t.ends_with(' ') => {
+                w - l.measure(*fl, &t.trim_end().replace(SHY, ""))
+            }
+            _ => 0,
+        };
+        Word { pieces, width, space }
+    }
+
+    // Split into a head no wider than room, and the rest. Soft hyphens are
+    // tried first; if hard is set, the word may be broken after any
+    // character instead, for words that are wider than a whole line.
+    fn split(&self, l: &Layout, room: usize, hard: bool) -> Option<(Word, Word)> {
+        let mut best = None;
+
+        for soft in [true, false] {
+            if !soft && !hard {
+                break;
+            }
+
+            let mut before = 0;
+            for (pi, (fl, text, w)) in self.pieces.iter().enumerate() {
+                for (bi, ch) in text.char_indices().skip(1) {
+                    if soft != (ch == SHY) || ch == ' ' {
+                        continue;
+                    }
+                    let mut head = text[..bi].replace(SHY, "");
+                    if soft {
+                        head.push('-');
+                    }
+                    if before + l.measure(*fl, &head) <= room {
+                        best = Some((pi, bi, soft));
+                    }
+                }
+                before += w;
+            }
+
+            if best.is_some() {
+                break;
+            }
+        }
+
+        let (pi, bi, soft) = best?;
+        let (fl, text, _) = &self.pieces[pi];
+
+        let mut head = self.pieces[..pi]
+            .iter()
+            .map(|(fl, t, _)| (*fl, t.clone()))
+            .collect::<Vec<_>>();
+        head.push((*fl, text[..bi].to_string() + if soft { "-" } else { "" }));
+
+        let mut tail = vec![(*fl, text[bi..].trim_start_matches(SHY).to_string())];
+        tail.extend(self.pieces[pi + 1..].iter().map(|(fl, t, _)| (*fl, t.clone())));
+
+        Some((Word::new(l, head), Word::new(l, tail)))
+    }
+}
+
+// Insert soft hyphens from the deck's hyphenation dictionary, if any.
+fn hyphenate(hyphens: &HashMap<String, Vec<usize>>, piece: &str) -> String {
+    if hyphens.is_empty() {
+        return piece.to_string();
+    }
+
+    let start = piece.find(char::is_alphabetic).unwrap_or(piece.len());
+    let end = piece[start..]
+        .find(|c: char| !c.is_alphabetic())
+        .map(|e| start + e)
+        .unwrap_or(piece.len());
+    let core = &piece[start..end];
+
+    match hyphens.get(&core.to_lowercase()) {
+        Some(breaks) => {
+            let mut out = piece[..start].to_string();
+            for (i, ch) in core.chars().enumerate() {
+                if breaks.contains(&i) {
+                    out.push(SHY);
+                }
+                out.push(ch);
+            }
+            out + &piece[end..]
+        }
+        None => piece.to_string(),
+    }
+}
+
+// Break spans into lines between lx and ex (the first line starting at x),
+// then position each line according to the alignment. Lines only break after
+// whitespace, so that e.g. "**foo**bar" stays together, or at soft hyphens.
+// Words that don't fit on a line by themselves are broken at the edge.
+//
+// Returns the text nodes, along with the end of the last line and its top.
+fn layout_text(
+    l: &Layout,
+    b: Bounds,
+    align: Align,
+    spans: &[(DrawFl, String)],
+) -> (Vec<Node<'static, 'static>>, usize, usize) {
+    let (lx, ex, sx, sy) = (b.lx, b.ex, b.x, b.y);
+    let mut words: VecDeque<Word> = VecDeque::new();
+    let mut cur = Vec::new();
+
+    for (fl, text) in spans {
+        for piece in text.split_inclusive(&[' ', '\n']) {
+            let piece = hyphenate(&l.config.hyphens, &piece.replace("\n", " "));
+            let end = piece.ends_with(' ');
+            cur.push((*fl, piece));
+            if end {
+                words.push_back(Word::new(l, std::mem::take(&mut cur)));
+            }
+        }
+    }
+    if !cur.is_empty() {
+        words.push_back(Word::new(l, cur));
+    }
+
+    let mut lines: Vec<Vec<Word>> = vec![Vec::new()];
+    let mut lw = 0;
+    while let Some(word) = words.pop_front() {
+        let start = if lines.len() == 1 { sx } else { lx };
+        let room = ex.saturating_sub(start + lw);
+        let empty = lines.last().unwrap().is_empty();
+
+        if word.width - word.space <= room {
+            lw += word.width;
+            lines.last_mut().unwrap().push(word);
+            continue;
+        }
+
+        if let Some((head, tail)) = word.split(l, room, empty) {
+            lines.last_mut().unwrap().push(head);
+            words.push_front(tail);
+        } else if empty {
+            // Not even a single character fits.
+            lines.last_mut().unwrap().push(word);
+        } else {
+            words.push_front(word);
+        }
+
+        lw = 0;
+        lines.push(Vec::new());
+    }
+    if lines.len() > 1 && lines.last().unwrap().is_empty() {
+        lines.pop();
+    }
+
+    let mut runs = Vec::new();
+    let (mut x, mut y) = (sx, sy);
+    let mut lh = 0;
+    let nlines = lines.len();
+
+    for (i, line) in lines.into_iter().enumerate() {
+        let start = if i == 0 { sx } else { lx };
+        let used = line.iter().map(|w| w.width).sum::<usize>()
+            - line.last().map(|w| w.space).unwrap_or(0);
+        let extra = (ex.saturating_sub(start)).saturating_sub(used);
+
+        y += lh;
+        lh = line
+            .iter()
+            .flat_map(|w| w.pieces.iter())
+            .map(|(fl, _, _)| l.line_height(*fl))
+            .max()
+            .unwrap_or(0);
+
+        x = match align {
+            Align::Left | Align::Justify => start,
+            Align::Center => start + extra / 2,
+            Align::Right => start + extra,
+        };
+
+        // Last line of a justified block is left as-is.
+        let gaps = line.len().saturating_sub(1);
+        let justify = align == Align::Justify && i + 1 < nlines && gaps > 0;
+
+        for (j, word) in line.into_iter().enumerate() {
+            let n = word.pieces.len();
+            for (k, (fl, text, w)) in word.pieces.into_iter().enumerate() {
+                let text = text.replace(SHY, "");
+                let ink = if k + 1 == n { w - word.space } else { w };
+                runs.push(Node::leaf(x, y, ink, lh, Kind::Text { fl, text, color: l.colors().fg, scale: l.text_scale() }));
+                x += w;
+            }
+            if justify && j < gaps {
+                x += extra / gaps + if j < extra % gaps { 1 } else { 0 };
+            }
+        }
+    }
+
+    (runs, x, y)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'n, 'p, 'a>(node: &'n Node<'p, 'a>, out: &mut Vec<&'n Node<'p, 'a>>) {
+        if let Kind::Text { .. } = node.kind {
+            out.push(node);
+        }
+        for c in &node.children {
+            texts(c, out);
+        }
+    }
+
+    // Bitmap fonts, and images of made-up sizes.
+    struct Sizes(Vec<(&'static str, (u32, u32))>);
+
+    impl Metrics for Sizes {
+        fn measure(&self, fl: DrawFl, text: &str) -> usize {
+            UfxMetrics { tracking: 0 }.measure(fl, text)
+        }
+
+        fn line_height(&self, fl: DrawFl) -> usize {
+            UfxMetrics { tracking: 0 }.line_height(fl)
+        }
+
+        fn image_size(&self, path: &Path) -> Option<(u32, u32)> {
+            self.0.iter().find(|(p, _)| Path::new(p) == path).map(|(_, s)| *s)
+        }
+    }
+
+    fn layout(deck: &Deck, slide: usize) -> (Vec<(usize, usize, usize, String)>, usize) {
+        layout_with(deck, slide, &Sizes(Vec::new()))
+    }
+
+    fn layout_with(deck: &Deck, slide: usize, metrics: &dyn Metrics) -> (Vec<(usize, usize, usize, String)>, usize) {
+        let images = HashMap::new();
+        let (tree, over) = Layout::for_deck(deck, &images, metrics).fitted(slide);
+
+        let mut nodes = Vec::new();
+        texts(&tree, &mut nodes);
+        let runs = nodes
+            .iter()
+            .map(|n| match &n.kind {
+                Kind::Text { text, .. } => (n.x, n.y, n.w, text.clone()),
+                _ => unreachable!(),
+            })
+            .collect();
+        (runs, over)
+    }
+
+    #[test]
+    fn wraps_within_padding() {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(8);
+        let deck = Deck::new().slide("Wrap", |s| s.text(&text));
+        let (runs, over) = layout(&deck, 0);
+
+        let pad = deck.config.padding;
+        assert!(runs.iter().all(|&(x, _, w, _)| x >= pad && x + w <= W - pad));
+        let mut lines = runs.iter().map(|r| r.1).collect::<Vec<_>>();
+        lines.dedup();
+        assert!(lines.len() > 2);
+        assert_eq!(over, 0);
+    }
+
+    #[test]
+    fn reports_overflow() {
+        let long = (0..40).fold(Deck::new(), |d, i| d.slide("", |s| s.text(&i.to_string())));
+        assert_eq!(layout(&long, 0).1, 0);
+
+        let deck = Deck::new().slide("Long", |s| (0..40).fold(s, |s, i| s.text(&i.to_string())));
+        assert!(layout(&deck, 0).1 > 0);
+    }
+
+    #[test]
+    fn places_text_below_scaled_images() {
+        let sizes = Sizes(vec![("wide.png", (8 * W as u32, 2 * H as u32))]);
+        let deck = Deck::new().slide("Wide", |s| s.image("wide.png").text("Below"));
+        let (runs, over) = layout_with(&deck, 0, &sizes);
+
+        let title = runs.iter().find(|r| r.3 == "Wide").unwrap();
+        let below = runs.iter().find(|r| r.3 == "Below").unwrap();
+        assert!(below.1 - title.1 < H / 2);
+        assert_eq!(over, 0);
+    }
+
+    #[test]
+    fn fit_tightens_spacing() {
+        let slide = |s: crate::build::SlideBuilder| (0..14).fold(s, |s, i| s.text(&i.to_string()));
+        let off = layout(&Deck::new().slide("Fit", slide), 0).1;
+        let fitted = layout(&Deck::new().fit(FitMode::Spacing).slide("Fit", slide), 0).1;
+        assert!(off > 0);
+        assert!(fitted < off);
+    }
+
+    #[test]
+    fn centers_title_slides() {
+        let deck = Deck::new().slide("Centered", |s| s.layout(SlideLayout::Title));
+        let (runs, _) = layout(&deck, 0);
+        let (x, _, w, ref text) = runs[0];
+        assert_eq!(text, "Centered");
+        assert!((x * 2 + w).abs_diff(W) <= 1);
+    }
+
+    #[test]
+    fn shows_byline_on_blank_title_slides() {
+        let deck = Deck::new()
+            .author("Ada")
+            .slide("Talk", |s| s.layout(SlideLayout::Title).markdown("\n"));
+        let (runs, _) = layout(&deck, 0);
+        assert!(runs.iter().any(|r| r.3 == "Ada"));
+    }
+
+    #[test]
+    fn spaces_big_statements_by_zoomed_lines() {
+        let deck = Deck::new().slide("", |s| {
+            s.layout(SlideLayout::BigStatement).markdown("# Big heading\n\nFirst paragraph\n\nSecond paragraph")
+        });
+        let (runs, _) = layout(&deck, 0);
+        let y = |word: &str| runs.iter().find(|r| r.3.trim() == word).unwrap().1;
+
+        let metrics = UfxMetrics { tracking: 0 };
+        assert!(y("First") >= y("Big") + 2 * metrics.line_height(DrawFl::HEAD));
+        assert!(y("Second") >= y("First") + 2 * metrics.line_height(DrawFl::NONE));
+    }
+
+    #[test]
+    fn numbers_visible_slides() {
+        let deck = Deck::new()
+            .footer(&["%n/%N", "", ""])
+            .slide("One", |s| s)
+            .slide("Backup", |s| s.hidden())
+            .slide("Two", |s| s);
+        let (runs, _) = layout(&deck, 2);
+        assert!(runs.iter().any(|r| r.3 == "2/2"));
+
+        let mut deck = deck;
+        deck.config.show_hidden = true;
+        let (runs, _) = layout(&deck, 2);
+        assert!(runs.iter().any(|r| r.3 == "3/3"));
+    }
+
+    #[test]
+    fn lays_out_header_variables() {
+        let deck = Deck::new()
+            .footer(&["%n/%N", "", "%t"])
+            .slide("One", |s| s)
+            .slide("Two", |s| s);
+        let (runs, _) = layout(&deck, 1);
+        let text = runs.iter().map(|r| r.3.as_str()).collect::<Vec<_>>().join(" ");
+        assert!(text.contains("2/2"));
+        assert!(text.ends_with("Two"));
+    }
+}
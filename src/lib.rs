@@ -0,0 +1,31 @@
+//! Slides from a markdown-ish text format, rendered with SDL.
+//!
+//! - [`md`]: the lexer and parser, from a deck file to a [`Deck`].
+//! - [`slide`]: the deck model, and [`Presentation`], a deck loaded for a
+//!   window.
+//! - [`layout`]: lays slides out and draws them.
+//! - [`uf2`]: the bitmap (UFX) fonts.
+//! - [`build`] and [`write`]: decks built in code, and written out as deck
+//!   files.
+//!
+//! Reading a deck and drawing its first slide:
+//!
+//! ```no_run
+//! # fn show(canvas: &mut sdl2::render::WindowCanvas) {
+//! let tcreator = canvas.texture_creator();
+//! let fcreator = sdl2::ttf::init().unwrap();
+//!
+//! let deck = eileda::md::parse(&eileda::md::lex("talk.eimd"));
+//! let p = eileda::Presentation::load(deck, &tcreator, &fcreator);
+//! p.draw(p.first(), canvas);
+//! # }
+//! ```
+
+pub mod build;
+pub mod layout;
+pub mod md;
+pub mod slide;
+pub mod uf2;
+pub mod write;
+
+pub use slide::{Content, Deck, Grid, Presentation, Slide};
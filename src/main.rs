@@ -1,105 +1,318 @@
-use sdl2;
-
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::image::InitFlag;
 use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use sdl2::video::FullscreenType;
+use std::process;
 use std::time::Duration;
 
-mod md;
-mod slide;
-mod uf2;
+use cli::{Command, Options};
+use keys::Action;
+use eileda::layout::{H, W};
+use eileda::md;
+use eileda::Presentation;
+
+mod check;
+mod cli;
+mod config;
+mod export;
+mod fonts;
+mod keys;
+mod overview;
+mod search;
 
 pub fn main() {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} file.eimd", args[0]);
-        return;
-    }
+    let prog = &args[0];
+
+    let code = match cli::parse(&args[1..]) {
+        Ok(Command::Present(opts)) => present(&opts),
+        Ok(Command::Export(opts, out)) => export::main(&opts, &out),
+        Ok(Command::Check(opts)) => check::main(&opts),
+        Ok(Command::Fonts(rest)) => fonts::main(prog, &rest),
+        Ok(Command::Help) => {
+            print!("{}", cli::usage(prog));
+            0
+        }
+        Ok(Command::Version) => {
+            println!("eileda {}", env!("CARGO_PKG_VERSION"));
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            eprint!("{}", cli::usage(prog));
+            2
+        }
+    };
+    process::exit(code);
+}
+
+fn present(opts: &Options) -> i32 {
+    let cfg = config::load();
 
     let sdl_context = sdl2::init().unwrap();
     let _image_context = sdl2::image::init(InitFlag::PNG | InitFlag::JPG).unwrap();
     let font_context = sdl2::ttf::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
 
-    let window = video_subsystem
-        .window("eileda", 960, 840) //1440, 810)
-        .position_centered()
-        .build()
-        .unwrap();
+    let (w, h) = opts.size.unwrap_or(cfg.size);
+    let mut builder = video_subsystem.window("eileda", w, h);
+    match opts.display.map(|d| (d, video_subsystem.display_bounds(d))) {
+        Some((_, Ok(b))) => {
+            builder.position(b.x() + (b.width() as i32 - w as i32) / 2, b.y() + (b.height() as i32 - h as i32) / 2);
+        }
+        Some((d, Err(e))) => {
+            eprintln!("Couldn't use display {}: {}", d, e);
+            builder.position_centered();
+        }
+        None => {
+            builder.position_centered();
+        }
+    }
+    if opts.fullscreen {
+        builder.fullscreen_desktop();
+    }
+    let window = builder.build().unwrap();
 
-    let mut canvas = window.into_canvas().build().unwrap();
+    let mut canvas = window.into_canvas().target_texture().build().unwrap();
     let texture_creator = canvas.texture_creator();
 
-    canvas.set_scale(2.0, 2.0).unwrap();
+    // For the search prompt.
+    video_subsystem.text_input().start();
 
-    let lexed = md::lex(&args[1]);
-    let mut stuff = md::parse(&texture_creator, &font_context, &lexed);
+    let lexed = md::lex(&opts.file);
+    let mut stuff = Presentation::load(md::parse(&lexed), &texture_creator, &font_context);
+    cfg.apply(&mut stuff);
+    stuff.config.ttf |= opts.ttf;
+    stuff.config.show_hidden = opts.hidden;
 
-    if stuff.slides.len() == 0 {
+    if stuff.slides.is_empty() {
         eprintln!("Presentation is empty");
-        return;
+        return 1;
     }
+    stuff.report_overflow();
+    canvas.window_mut().set_title(&stuff.window_title()).unwrap();
 
-    let mut cur = 0;
-    stuff.draw(cur, &mut canvas);
+    let mut cur = match opts.start {
+        Some(n) => n.min(stuff.slides.len()) - 1,
+        None => stuff.first(),
+    };
+    let mut mode = Mode::Slides;
+    // Screen color while blacked or whited out. This only covers the window;
+    // the slide and mode underneath are left as they are.
+    let mut blank: Option<Color> = None;
+    show(&mut canvas, &stuff, cur, &mode, blank);
+
+    // Slide number being typed, and the one typed just before the last key.
+    let mut typed: Option<usize> = None;
+    let mut number = None;
 
     let mut event_pump = sdl_context.event_pump().unwrap();
     'running: loop {
         for event in event_pump.poll_iter() {
             match event {
-                Event::KeyDown {
-                    keycode: Some(Keycode::Left),
+                Event::Quit { .. } => break 'running,
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
                     ..
                 } => {
-                    if cur > 0 {
-                        cur -= 1;
-                    }
-                    stuff.draw(cur, &mut canvas);
+                    show(&mut canvas, &stuff, cur, &mode, blank);
+                    continue;
                 }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Right),
-                    ..
+                _ => {}
+            }
+
+            // Any key or click brings the slide back.
+            if blank.is_some() {
+                if let Event::KeyDown { .. } | Event::MouseButtonDown { .. } = event {
+                    blank = None;
+                    show(&mut canvas, &stuff, cur, &mode, blank);
                 }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Space),
-                    ..
-                } => {
-                    if cur < stuff.slides.len() - 1 {
-                        cur += 1;
+                continue;
+            }
+
+            if let Mode::Overview(ov) = &mut mode {
+                match event {
+                    Event::KeyDown { keycode: Some(k), .. } => match k {
+                        Keycode::Left => ov.step(-1, 0),
+                        Keycode::Right => ov.step(1, 0),
+                        Keycode::Up => ov.step(0, -1),
+                        Keycode::Down => ov.step(0, 1),
+                        Keycode::Return => {
+                            cur = ov.sel;
+                            mode = Mode::Slides;
+                        }
+                        Keycode::O | Keycode::Tab | Keycode::Escape => mode = Mode::Slides,
+                        _ => {}
+                    },
+                    Event::MouseButtonDown { x, y, clicks, .. } => {
+                        let (x, y) = to_canvas(&canvas, x, y);
+                        if let Some(i) = ov.hit(x, y) {
+                            ov.select(i);
+                            if clicks >= 2 {
+                                cur = i;
+                                mode = Mode::Slides;
+                            }
+                        }
                     }
-                    stuff.draw(cur, &mut canvas);
+                    Event::MouseWheel { y, .. } => ov.scroll(-y as isize),
+                    _ => continue,
                 }
-                Event::KeyDown {
-                    keycode: Some(Keycode::R),
-                    ..
-                } => {
-                    let lexed = md::lex(&args[1]);
-                    stuff = md::parse(&texture_creator, &font_context, &lexed);
 
-                    if stuff.slides.len() == 0 {
+                show(&mut canvas, &stuff, cur, &mode, blank);
+                continue;
+            }
+
+            if let Mode::Search(search) = &mut mode {
+                match event {
+                    // Typing the / that opened the search also sends it as text.
+                    Event::TextInput { text, .. } if text == "/" && search.query.is_empty() => continue,
+                    Event::TextInput { text, .. } => {
+                        search.query.push_str(&text);
+                        search.update(&stuff);
+                    }
+                    Event::KeyDown { keycode: Some(k), .. } => match k {
+                        Keycode::Backspace => {
+                            search.query.pop();
+                            search.update(&stuff);
+                        }
+                        Keycode::Up => search.step(-1),
+                        Keycode::Down | Keycode::Tab => search.step(1),
+                        Keycode::Return | Keycode::KpEnter => {
+                            cur = search.current().unwrap_or(cur);
+                            mode = Mode::Slides;
+                        }
+                        Keycode::Escape => mode = Mode::Slides,
+                        _ => continue,
+                    },
+                    _ => continue,
+                }
+
+                show(&mut canvas, &stuff, cur, &mode, blank);
+                continue;
+            }
+
+            let action = match event {
+                Event::KeyDown { keycode: Some(k), .. } => match keys::digit(k) {
+                    Some(d) => {
+                        typed = Some(typed.unwrap_or(0).saturating_mul(10).saturating_add(d));
+                        None
+                    }
+                    None => {
+                        number = typed.take();
+                        keys::key(&cfg.keys, k)
+                    }
+                },
+                Event::MouseButtonDown { mouse_btn, .. } => keys::button(keys::BUTTONS, mouse_btn),
+                Event::MouseWheel { y, .. } => keys::wheel(y),
+                _ => None,
+            };
+
+            match action {
+                Some(Action::Next) => cur = stuff.next(cur),
+                Some(Action::Prev) => cur = stuff.prev(cur),
+                Some(Action::First) => cur = stuff.first(),
+                Some(Action::Last) => cur = stuff.last(),
+                Some(Action::Goto) => match number.take() {
+                    Some(n) => cur = n.clamp(1, stuff.slides.len()) - 1,
+                    None => continue,
+                },
+                Some(Action::Reload) => {
+                    let lexed = md::lex(&opts.file);
+                    stuff = Presentation::load(md::parse(&lexed), &texture_creator, &font_context);
+                    cfg.apply(&mut stuff);
+                    stuff.config.ttf |= opts.ttf;
+                    stuff.config.show_hidden = opts.hidden;
+
+                    if stuff.slides.is_empty() {
                         eprintln!("Presentation is empty");
-                        return;
+                        return 1;
                     }
+                    stuff.report_overflow();
+                    canvas.window_mut().set_title(&stuff.window_title()).unwrap();
                     cur = cur.min(stuff.slides.len() - 1);
-                    stuff.draw(cur, &mut canvas);
                 }
-                Event::KeyDown {
-                    keycode: Some(Keycode::T),
-                    ..
-                } => {
+                Some(Action::ToggleTtf) => {
                     stuff.config.ttf = !stuff.config.ttf;
-                    stuff.draw(cur, &mut canvas);
+                    stuff.report_overflow();
                 }
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => break 'running,
-                _ => {}
+                Some(Action::Overview) => {
+                    match overview::Overview::new(&stuff, &mut canvas, &texture_creator, cur) {
+                        Ok(ov) => mode = Mode::Overview(ov),
+                        Err(e) => eprintln!("Couldn't open overview: {}", e),
+                    }
+                }
+                Some(Action::Fullscreen) => {
+                    let window = canvas.window_mut();
+                    let to = match window.fullscreen_state() {
+                        FullscreenType::Off => FullscreenType::Desktop,
+                        _ => FullscreenType::Off,
+                    };
+                    if let Err(e) = window.set_fullscreen(to) {
+                        eprintln!("Couldn't toggle fullscreen: {}", e);
+                    }
+                }
+                Some(Action::Search) => mode = Mode::Search(search::Search::default()),
+                Some(Action::Blackout) => blank = Some(Color::RGB(0, 0, 0)),
+                Some(Action::Whiteout) => blank = Some(Color::RGB(255, 255, 255)),
+                Some(Action::Quit) => break 'running,
+                None => continue,
             }
+            show(&mut canvas, &stuff, cur, &mode, blank);
         }
 
         canvas.present();
         ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
     }
+    0
+}
+
+// What the window shows, besides the current slide.
+enum Mode<'a> {
+    Slides,
+    Overview(overview::Overview<'a>),
+    Search(search::Search),
+}
+
+// Scale the canvas so that slides fill as much of the window as they can,
+// centered, with black bars around them if the aspect ratio differs.
+fn fit(canvas: &mut WindowCanvas) {
+    let (w, h) = canvas.output_size().unwrap();
+    let s = (w as f32 / W as f32).min(h as f32 / H as f32);
+    canvas.set_scale(s, s).unwrap();
+
+    let (ox, oy) = ((w as f32 / s - W as f32) / 2.0, (h as f32 / s - H as f32) / 2.0);
+    canvas.set_viewport(Rect::new(ox as i32, oy as i32, W as u32, H as u32));
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+}
+
+// Window coordinates to canvas ones.
+fn to_canvas(canvas: &WindowCanvas, x: i32, y: i32) -> (usize, usize) {
+    let (sx, sy) = canvas.scale();
+    let vp = canvas.viewport();
+    let x = (x as f32 / sx) as i32 - vp.x();
+    let y = (y as f32 / sy) as i32 - vp.y();
+    (x.max(0) as usize, y.max(0) as usize)
+}
+
+fn show(
+    canvas: &mut WindowCanvas,
+    p: &Presentation,
+    cur: usize,
+    mode: &Mode,
+    blank: Option<Color>,
+) {
+    fit(canvas);
+    match (blank, mode) {
+        (Some(color), _) => {
+            canvas.set_draw_color(color);
+            canvas.clear();
+        }
+        (None, Mode::Overview(ov)) => ov.draw(p, cur, canvas),
+        (None, Mode::Search(search)) => search.draw(p, cur, canvas),
+        (None, Mode::Slides) => p.draw(cur, canvas),
+    }
 }
@@ -1,13 +1,11 @@
-use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 use crate::slide::*;
-use sdl2::image::LoadTexture;
-use sdl2::render::TextureCreator;
-use sdl2::ttf::Sdl2TtfContext;
-use sdl2::video::WindowContext;
-use sdl2::rwops::RWops;
 
 #[derive(Clone, Debug)]
 pub enum Item {
@@ -19,174 +17,620 @@ pub enum Item {
     BeginGrid(usize),
     NextColumn,
     EndGrid,
+    Align(Align),
+    Tracking(usize),
+    Kerning(bool),
+    Hyphens(PathBuf),
+    Fit(FitMode),
+    Layout(SlideLayout),
+    Header(Option<Vec<String>>),
+    Footer(Option<Vec<String>>),
+    Title(String),
+    Author(String),
+    Date(String),
+    Event(String),
+    Hidden,
 }
 
-pub fn lex(file: &str) -> Vec<Item> {
-    let data = match fs::read_to_string(file) {
-        Ok(s) => s,
-        Err(_) => {
-            eprintln!("Nuh uh");
+// Where a line came from, for diagnostics.
+#[derive(Clone, Debug)]
+pub struct Loc {
+    pub file: Rc<str>,
+    pub line: usize,
+}
+
+impl fmt::Display for Loc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+// A problem found while reading a deck. code is a short, stable name for the
+// kind of problem, for tools.
+#[derive(Clone, Debug)]
+pub struct Diag {
+    pub loc: Loc,
+    pub code: &'static str,
+    pub msg: String,
+}
+
+impl fmt::Display for Diag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: Error: {}", self.loc, self.msg)
+    }
+}
+
+pub fn diag(diags: &mut Vec<Diag>, loc: &Loc, code: &'static str, msg: String) {
+    diags.push(Diag { loc: loc.clone(), code, msg });
+}
+
+#[derive(Clone, Debug)]
+struct Line {
+    loc: Loc,
+    text: String,
+}
+
+impl Item {
+    // Name of the variant, for diagnostics.
+    pub fn name(&self) -> String {
+        let s = format!("{:?}", self);
+        s.split('(').next().unwrap_or("").to_string()
+    }
+}
+
+pub fn lex(file: &str) -> Vec<(Loc, Item)> {
+    let mut diags = Vec::new();
+    let items = lex_with(file, &mut diags);
+    for d in diags {
+        eprintln!("{}", d);
+    }
+    items
+}
+
+// Same as lex, but collecting problems rather than printing them.
+pub fn lex_with(file: &str, diags: &mut Vec<Diag>) -> Vec<(Loc, Item)> {
+    let mut stack = Vec::new();
+    let lines = match read_lines(Path::new(file), &mut stack, diags) {
+        Ok(l) => l,
+        Err(e) => {
+            let loc = Loc { file: file.into(), line: 0 };
+            diag(diags, &loc, "read", format!("Couldn't read {}: {}", file, e));
             return Vec::new();
         }
     };
 
+    let mut templates = HashMap::new();
+    let mut imports = fs::canonicalize(file).into_iter().collect();
+    let lines = expand_templates(lines, &mut templates, &mut imports, diags);
+    let lines = expand_macros(lines, &mut Macros::default(), 0, diags);
+
     let mut items = Vec::new();
     let mut mdbuf = String::new();
+    let mut mdloc = None;
 
-    for line in data.lines() {
+    for Line { loc, text: line } in lines.iter() {
         if line.starts_with(".") && !line.starts_with("..") {
-            if mdbuf != "" {
-                items.push(Item::Md(
-                    markdown::to_mdast(&mdbuf, &markdown::ParseOptions {
-                        constructs: markdown::Constructs {
-                            hard_break_trailing: false,
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    }).unwrap(),
-                ));
+            if let Some(l) = mdloc.take() {
+                items.push((l, Item::Md(to_mdast(&mdbuf))));
                 mdbuf.clear();
             }
 
             let cmd = line.split(" ").collect::<Vec<_>>();
             match cmd[0] {
-                ".SLD" => {
+                ".SLD" | ".SLD!" => {
                     let t = if cmd.len() == 1 { None } else {
                         Some(line[cmd[0].len() + 1..].to_string())
                     };
-                    items.push(Item::BeginSlide(t));
+                    items.push((loc.clone(), Item::BeginSlide(t)));
+                    if cmd[0] == ".SLD!" {
+                        items.push((loc.clone(), Item::Hidden));
+                    }
+                }
+                ".HID" if cmd.len() == 1 => {
+                    items.push((loc.clone(), Item::Hidden));
                 }
                 ".MAR" if cmd.len() == 3 => {
-                    items.push(Item::Margin(
+                    items.push((loc.clone(), Item::Margin(
                         cmd[1].parse().unwrap_or(0),
                         PathBuf::from(cmd[2]),
-                    ));
+                    )));
                 }
                 ".PAD" if cmd.len() == 2 => {
-                    items.push(Item::Pad(cmd[1].parse().unwrap_or(0)));
+                    items.push((loc.clone(), Item::Pad(cmd[1].parse().unwrap_or(0))));
                 }
                 ".IMG" if cmd.len() == 2 => {
-                    items.push(Item::Img(PathBuf::from(cmd[1])));
+                    items.push((loc.clone(), Item::Img(PathBuf::from(cmd[1]))));
                 }
                 ".GRD" => {
                     if cmd.len() <= 2 {
                         if cmd.len() == 1 {
-                            items.push(Item::BeginGrid(50));
+                            items.push((loc.clone(), Item::BeginGrid(50)));
                         } else if let Ok(rat) = cmd[1].parse() {
-                            items.push(Item::BeginGrid(rat));
+                            items.push((loc.clone(), Item::BeginGrid(rat)));
                         } else if cmd[1] == "end" {
-                            items.push(Item::EndGrid);
+                            items.push((loc.clone(), Item::EndGrid));
                         } else {
-                            eprintln!("Error: Bad grid directive");
+                            diag(diags, loc, "bad-argument", "Bad grid directive".to_string());
                         }
                     }
                 }
                 ".COL" => {
                     if cmd.len() == 1 {
-                        items.push(Item::NextColumn);
+                        items.push((loc.clone(), Item::NextColumn));
+                    }
+                }
+                ".ALN" if cmd.len() == 2 => {
+                    match cmd[1] {
+                        "left" => items.push((loc.clone(), Item::Align(Align::Left))),
+                        "center" => items.push((loc.clone(), Item::Align(Align::Center))),
+                        "right" => items.push((loc.clone(), Item::Align(Align::Right))),
+                        "justify" => items.push((loc.clone(), Item::Align(Align::Justify))),
+                        a => diag(diags, loc, "bad-argument", format!("Bad alignment: {}", a)),
+                    }
+                }
+                ".HYP" if cmd.len() == 2 => {
+                    items.push((loc.clone(), Item::Hyphens(PathBuf::from(cmd[1]))));
+                }
+                ".LAY" if cmd.len() == 2 => {
+                    match cmd[1] {
+                        "content" => items.push((loc.clone(), Item::Layout(SlideLayout::Content))),
+                        "title" => items.push((loc.clone(), Item::Layout(SlideLayout::Title))),
+                        "section" => items.push((loc.clone(), Item::Layout(SlideLayout::Section))),
+                        "two-column" => items.push((loc.clone(), Item::Layout(SlideLayout::TwoColumn))),
+                        "big-statement" => items.push((loc.clone(), Item::Layout(SlideLayout::BigStatement))),
+                        "image-full" => items.push((loc.clone(), Item::Layout(SlideLayout::ImageFull))),
+                        l => diag(diags, loc, "bad-argument", format!("Unknown layout: {}", l)),
                     }
                 }
-                c => eprintln!("Error: Unknown or invalid directive: {}", c),
+                ".TTL" | ".ATH" | ".DTE" | ".EVT" if cmd.len() > 1 => {
+                    let value = line[cmd[0].len() + 1..].to_string();
+                    items.push((loc.clone(), match cmd[0] {
+                        ".TTL" => Item::Title(value),
+                        ".ATH" => Item::Author(value),
+                        ".DTE" => Item::Date(value),
+                        _ => Item::Event(value),
+                    }));
+                }
+                ".HDR" | ".FTR" if cmd.len() > 1 => {
+                    let segments = match cmd[1..].join(" ").as_str() {
+                        "off" => None,
+                        s => Some(s.split('|').map(|seg| seg.trim().to_string()).collect()),
+                    };
+                    items.push((loc.clone(), match cmd[0] {
+                        ".HDR" => Item::Header(segments),
+                        _ => Item::Footer(segments),
+                    }));
+                }
+                ".FIT" if cmd.len() == 2 => {
+                    match cmd[1] {
+                        "off" => items.push((loc.clone(), Item::Fit(FitMode::Off))),
+                        "spacing" => items.push((loc.clone(), Item::Fit(FitMode::Spacing))),
+                        "shrink" => items.push((loc.clone(), Item::Fit(FitMode::Shrink))),
+                        f => diag(diags, loc, "bad-argument", format!("Bad fit mode: {}", f)),
+                    }
+                }
+                ".TRK" if cmd.len() == 2 => {
+                    items.push((loc.clone(), Item::Tracking(cmd[1].parse().unwrap_or(0))));
+                }
+                ".KRN" if cmd.len() == 2 => {
+                    match cmd[1] {
+                        "on" => items.push((loc.clone(), Item::Kerning(true))),
+                        "off" => items.push((loc.clone(), Item::Kerning(false))),
+                        k => diag(diags, loc, "bad-argument", format!("Bad kerning directive: {}", k)),
+                    }
+                }
+                c => diag(diags, loc, "unknown-directive", format!("Unknown or invalid directive: {}", c)),
             }
         } else {
+            if mdbuf.is_empty() {
+                mdloc = Some(loc.clone());
+            }
+            // ".." escapes a literal leading dot.
+            let line = line.strip_prefix('.').unwrap_or(line);
             mdbuf.push_str(&(line.to_owned() + "\n"));
         }
     }
 
-    items.push(Item::Md(
+    let loc = mdloc
+        .or_else(|| lines.last().map(|l| l.loc.clone()))
+        .unwrap_or(Loc { file: file.into(), line: 0 });
+    items.push((loc, Item::Md(
         markdown::to_mdast(&mdbuf, &Default::default()).unwrap(),
-    ));
+    )));
     mdbuf.clear();
 
     items
 }
 
-pub fn parse<'a>(
-    tcreator: &'a TextureCreator<WindowContext>,
-    fcreator: &'a Sdl2TtfContext,
-    items: &Vec<Item>,
-) -> Presentation<'a> {
-    macro_rules! embed_ttf {
-        ($path:literal, $sz:literal) => {
-            {
-                let data = include_bytes!($path);
-                let rwops = Box::new(RWops::from_bytes(data).unwrap());
-                RefCell::new(
-                    fcreator.load_font_from_rwops(*rwops, $sz).unwrap()
-                )
+// Markdown between directives, as the lexer reads it.
+pub fn to_mdast(src: &str) -> markdown::mdast::Node {
+    markdown::to_mdast(src, &markdown::ParseOptions {
+        constructs: markdown::Constructs {
+            hard_break_trailing: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    }).unwrap()
+}
+
+// Read a deck, splicing in the lines of any file it includes with .INC. Paths
+// are relative to the including file. stack holds the files currently being
+// read, to catch include cycles. Comments are dropped here, so that they can
+// hide anything, including other directives.
+fn read_lines(path: &Path, stack: &mut Vec<PathBuf>, diags: &mut Vec<Diag>) -> io::Result<Vec<Line>> {
+    let data = fs::read_to_string(path)?;
+    let file: Rc<str> = path.to_string_lossy().into();
+    stack.push(fs::canonicalize(path)?);
+
+    let mut out = Vec::new();
+    let mut ignored: Option<(Loc, usize)> = None;
+
+    for (i, text) in data.lines().enumerate() {
+        let loc = Loc { file: file.clone(), line: i + 1 };
+        let cmd = text.split(" ").next().unwrap_or("");
+
+        // A .IG block runs up to its .END, skipping any blocks nested in it.
+        if let Some((_, depth)) = &mut ignored {
+            match cmd {
+                ".IG" | ".TPL" | ".MAC" => *depth += 1,
+                ".END" => *depth -= 1,
+                _ => {}
+            }
+            if *depth == 0 {
+                ignored = None;
             }
+            continue;
+        }
+        if cmd == ".IG" {
+            ignored = Some((loc, 1));
+            continue;
+        }
+        if text.starts_with(".\\\"") {
+            continue;
+        }
+
+        let inc = match text.strip_prefix(".INC ") {
+            Some(inc) => relative_to(path, inc.trim()),
+            None => {
+                out.push(Line { loc, text: text.to_string() });
+                continue;
+            }
+        };
+
+        if fs::canonicalize(&inc).is_ok_and(|c| stack.contains(&c)) {
+            diag(diags, &loc, "include", format!("Include cycle: {}", inc.display()));
+            continue;
+        }
+        match read_lines(&inc, stack, diags) {
+            Ok(lines) => out.extend(lines),
+            Err(e) => diag(diags, &loc, "include", format!("Couldn't include {}: {}", inc.display(), e)),
         }
     }
 
-    let mut p = Presentation {
-        tcreator,
-        config: GlobalConfig {
-            padding: 16,
-            margin: None,
-            ttf: false,
-        },
-        slides: Vec::new(),
+    if let Some((loc, _)) = ignored {
+        diag(diags, &loc, "unterminated", "Unterminated .IG".to_string());
+    }
 
-        f_bold_68: embed_ttf!("../assets/ttf/Inter-Bold.otf", 68),
-        f_norm_24: embed_ttf!("../assets/ttf/Inter-Regular.otf", 24),
-        f_bold_24: embed_ttf!("../assets/ttf/Inter-Bold.otf", 24),
-        f_emph_24: embed_ttf!("../assets/ttf/Inter-Italic.otf", 24),
-        f_both_24: embed_ttf!("../assets/ttf/Inter-BoldItalic.otf", 24),
-    };
+    stack.pop();
+    Ok(out)
+}
 
-    let mut last_title = None;
+fn relative_to(file: &Path, path: &str) -> PathBuf {
+    file.parent().unwrap_or(Path::new("")).join(path)
+}
 
-    let _push = |p: &mut Presentation<'a>, content: Content<'a>| {
-        let slide_last_idx = p.slides.len() - 1;
+// Template definitions and instances are expanded here, before anything else
+// is lexed, so that placeholders can appear in directives too.
+//
+//   .TPL name          Define a template, up to the next .END.
+//   .TPI path          Import the templates defined in another file.
+//   .SLD @name title   Instantiate a template. The lines up to the next .SLD
+//                      are its {body}, except for .ARG lines.
+//   .ARG key value     Value for the {key} placeholder.
+//
+// {title} is the slide's title. Template lines that refer to a placeholder
+// without a value are left out.
+fn expand_templates(
+    lines: Vec<Line>,
+    templates: &mut HashMap<String, Vec<Line>>,
+    imports: &mut Vec<PathBuf>,
+    diags: &mut Vec<Diag>,
+) -> Vec<Line> {
+    let mut out = Vec::new();
+    let mut lines = lines.into_iter().peekable();
 
-        if p.slides[slide_last_idx].content.len() == 0 {
-            p.slides[slide_last_idx].content.push(content);
-            return;
+    while let Some(line) = lines.next() {
+        let loc = &line.loc;
+        let cmd = line.text.split(" ").collect::<Vec<_>>();
+        match cmd[0] {
+            ".TPL" if cmd.len() == 2 => {
+                let mut body = Vec::new();
+                loop {
+                    match lines.next() {
+                        Some(l) if l.text == ".END" => break,
+                        Some(l) => body.push(l),
+                        None => {
+                            diag(diags, loc, "unterminated", format!("Unterminated template: {}", cmd[1]));
+                            break;
+                        }
+                    }
+                }
+                templates.insert(cmd[1].to_string(), body);
+            }
+            ".TPI" if cmd.len() == 2 => {
+                let path = relative_to(Path::new(&*loc.file), cmd[1]);
+                // Imports may import others, but not themselves.
+                let canon = fs::canonicalize(&path).unwrap_or_default();
+                if imports.contains(&canon) {
+                    diag(diags, loc, "include", format!("Import cycle: {}", path.display()));
+                    continue;
+                }
+                match read_lines(&path, &mut Vec::new(), diags) {
+                    Ok(l) => {
+                        imports.push(canon);
+                        expand_templates(l, templates, imports, diags);
+                        imports.pop();
+                    }
+                    Err(e) => diag(diags, loc, "include", format!("Couldn't import templates from {}: {}", path.display(), e)),
+                }
+            }
+            // Titles may start with an @ of their own, e.g. a handle, unless
+            // it names a template.
+            ".SLD" | ".SLD!" if cmd.len() > 1 && cmd[1].strip_prefix('@').is_some_and(|n| templates.contains_key(n)) => {
+                let tpl = &templates[&cmd[1][1..]];
+                let title = cmd[2..].join(" ");
+
+                let mut vars = HashMap::new();
+                let mut body = Vec::new();
+                while let Some(l) = lines.next_if(|l| !is_slide_boundary(&l.text)) {
+                    match l.text.strip_prefix(".ARG ").and_then(|a| a.split_once(' ')) {
+                        Some((k, v)) => _ = vars.insert(k.to_string(), v.to_string()),
+                        None => body.push(l),
+                    }
+                }
+                if !title.is_empty() {
+                    vars.insert("title".to_string(), title.clone());
+                }
+                let blank = body.iter().all(|l| l.text.trim().is_empty());
+                if !blank {
+                    let text = body.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n");
+                    vars.insert("body".to_string(), text);
+                }
+
+                let text = if title.is_empty() { cmd[0].to_string() } else { format!("{} {}", cmd[0], title) };
+                out.push(Line { loc: loc.clone(), text });
+                for tline in tpl {
+                    // The body keeps its own locations.
+                    if tline.text == "{body}" {
+                        if !blank {
+                            out.extend(body.iter().cloned());
+                        }
+                    } else if let Some(text) = substitute(&tline.text, &vars) {
+                        let loc = &tline.loc;
+                        out.extend(text.lines().map(|t| Line { loc: loc.clone(), text: t.to_string() }));
+                    }
+                }
+            }
+            _ => out.push(line),
         }
+    }
+
+    out
+}
 
-        let content_last_idx = p.slides[slide_last_idx].content.len() - 1;
-        match p.slides[slide_last_idx].content[content_last_idx] {
-            Content::Grid(Grid {
-                ref mut first,
-                _parser_col_adv,
-                ..
-            }) if _parser_col_adv == false => {
-                first.push(content);
+// Variables and macros are expanded after templates, so that templates may use
+// them too.
+//
+//   .DEF name value    Define {{name}}, for use in any later line.
+//   .MAC NAME a b      Define a macro, up to the next .END, with parameters
+//                      {{a}} and {{b}}. It is invoked as .NAME x y, where the
+//                      last argument takes the rest of the line.
+#[derive(Default)]
+struct Macros {
+    vars: HashMap<String, String>,
+    macros: HashMap<String, (Vec<String>, Vec<Line>)>,
+}
+
+const MAX_MACRO_DEPTH: usize = 16;
+
+fn expand_macros(lines: Vec<Line>, m: &mut Macros, depth: usize, diags: &mut Vec<Diag>) -> Vec<Line> {
+    let mut out = Vec::new();
+    let mut lines = lines.into_iter();
+
+    while let Some(mut line) = lines.next() {
+        // Macro bodies are only interpolated once they're invoked.
+        if !line.text.starts_with(".MAC ") {
+            line.text = interpolate(&line.loc, &line.text, &m.vars, diags);
+        }
+
+        let loc = &line.loc;
+        let cmd = line.text.split(" ").collect::<Vec<_>>();
+        match cmd[0] {
+            ".DEF" if cmd.len() > 1 => {
+                m.vars.insert(cmd[1].to_string(), cmd[2..].join(" "));
             }
-            Content::Grid(Grid {
-                ref mut second,
-                _parser_col_adv,
-                ..
-            }) if _parser_col_adv == true => {
-                second.push(content);
+            ".MAC" if cmd.len() > 1 => {
+                let params = cmd[2..].iter().map(|p| p.to_string()).collect();
+                let mut body = Vec::new();
+                loop {
+                    match lines.next() {
+                        Some(l) if l.text == ".END" => break,
+                        Some(l) => body.push(l),
+                        None => {
+                            diag(diags, loc, "unterminated", format!("Unterminated macro: {}", cmd[1]));
+                            break;
+                        }
+                    }
+                }
+                m.macros.insert(format!(".{}", cmd[1]), (params, body));
             }
+            c if m.macros.contains_key(c) => {
+                if depth >= MAX_MACRO_DEPTH {
+                    diag(diags, loc, "macro", format!("Macros nested too deeply: {}", c));
+                    continue;
+                }
+
+                let (params, body) = m.macros[c].clone();
+                let rest = line.text[c.len()..].trim();
+                let args = if rest.is_empty() {
+                    Vec::new()
+                } else {
+                    rest.splitn(params.len().max(1), ' ').collect::<Vec<_>>()
+                };
+                if args.len() != params.len() {
+                    diag(diags, loc, "macro", format!("{} takes {} argument(s), got {}", c, params.len(), args.len()));
+                    continue;
+                }
+
+                let body = body
+                    .into_iter()
+                    .map(|mut l| {
+                        for (p, a) in params.iter().zip(&args) {
+                            l.text = l.text.replace(&format!("{{{{{}}}}}", p), a);
+                        }
+                        l
+                    })
+                    .collect();
+                out.extend(expand_macros(body, m, depth + 1, diags));
+            }
+            _ => out.push(line),
+        }
+    }
+
+    out
+}
+
+// Replace {{name}} with the value of a variable defined with .DEF.
+fn interpolate(loc: &Loc, text: &str, vars: &HashMap<String, String>, diags: &mut Vec<Diag>) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(e) => start + e + 2,
+            None => break,
+        };
+        let name = rest[start + 2..end - 2].trim();
+
+        out.push_str(&rest[..start]);
+        match vars.get(name) {
+            Some(v) => out.push_str(v),
+            None => {
+                diag(diags, loc, "undefined-variable", format!("Undefined variable: {}", name));
+                out.push_str(&rest[start..end]);
+            }
+        }
+        rest = &rest[end..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn is_slide_boundary(line: &str) -> bool {
+    let cmd = line.split(" ").next().unwrap_or("");
+    cmd == ".SLD" || cmd == ".SLD!" || cmd == ".TPL" || cmd == ".TPI"
+}
+
+// Replace {key} placeholders, or None if one of them has no value.
+fn substitute(line: &str, vars: &HashMap<String, String>) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find('{') {
+        // {{name}} is a variable, expanded later on.
+        if let Some(end) = rest[start..].strip_prefix("{{").and_then(|r| r.find("}}")) {
+            out.push_str(&rest[..start + end + 4]);
+            rest = &rest[start + end + 4..];
+            continue;
+        }
+
+        let key = rest[start + 1..]
+            .find('}')
+            .map(|end| &rest[start + 1..start + 1 + end])
+            .filter(|k| !k.is_empty() && k.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-'));
+
+        match key {
+            Some(k) => {
+                out.push_str(&rest[..start]);
+                out.push_str(vars.get(k)?);
+                rest = &rest[start + k.len() + 2..];
+            }
+            None => {
+                out.push_str(&rest[..start + 1]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    Some(out)
+}
+
+pub fn parse(items: &[(Loc, Item)]) -> Deck {
+    let mut diags = Vec::new();
+    let deck = parse_with(items, &mut diags);
+    for d in diags {
+        eprintln!("{}", d);
+    }
+    deck
+}
+
+// Same as parse, but collecting problems rather than printing them.
+pub fn parse_with(items: &[(Loc, Item)], diags: &mut Vec<Diag>) -> Deck {
+    let mut p = Deck::default();
+
+    let mut last_title = None;
+
+    let _push = |p: &mut Deck, content: Content| {
+        let slide_last_idx = p.slides.len() - 1;
+
+        match p.slides[slide_last_idx].content.last_mut() {
+            Some(Content::Grid(g)) if !g.closed => g.column().push(content),
             _ => p.slides[slide_last_idx].content.push(content),
         }
     };
 
-    for item in items.into_iter() {
-        if p.slides.len() == 0 {
+    for (loc, item) in items.iter() {
+        if p.slides.is_empty() {
             match item {
                 Item::Pad(pad) => p.config.padding = *pad,
-                Item::Margin(middle_size, path) => p.config.margin = Some(Margin {
-                    image: match tcreator.load_texture(path) {
-                        Ok(t) => t,
-                        Err(s) => {
-                            eprintln!("Couldn't load image: {}", s);
-                            continue;
-                        }
-                    },
-                    middle: *middle_size,
-                }),
+                Item::Align(a) => p.config.align = *a,
+                Item::Tracking(t) => p.config.tracking = *t,
+                Item::Fit(f) => p.config.fit = *f,
+                Item::Title(t) => p.meta.title = Some(t.clone()),
+                Item::Author(a) => p.meta.author = Some(a.clone()),
+                Item::Date(d) => p.meta.date = Some(d.clone()),
+                Item::Event(e) => p.meta.event = Some(e.clone()),
+                Item::Header(h) => p.config.header = h.clone(),
+                Item::Footer(f) => p.config.footer = f.clone(),
+                Item::Hyphens(path) => match fs::read_to_string(path) {
+                    Ok(dict) => load_hyphens(&mut p.config.hyphens, &dict),
+                    Err(e) => diag(diags, loc, "missing-file", format!("Couldn't load hyphenation dictionary: {}", e)),
+                },
+                Item::Kerning(k) => p.config.kerning = *k,
+                Item::Margin(middle_size, path) => {
+                    missing_image(path, loc, diags);
+                    p.config.margin = Some(Margin {
+                        image: path.clone(),
+                        middle: *middle_size,
+                    });
+                }
                 Item::BeginSlide(t) => {
                     let newt = t.clone().or(last_title.clone());
                     p.slides.push(Slide {
                         title: newt.clone(),
                         content: Vec::new(),
+                        fit: None,
+                        layout: None,
+                        header: true,
+                        footer: true,
+                        hidden: false,
                     });
                     last_title = newt.clone();
                 },
-                _ => eprintln!("Unexpected headers: {:?}", item),
+                Item::Md(md) if is_blank(md) => {}
+                _ => diag(diags, loc, "content-before-slide", format!("{} before the first .SLD", item.name())),
             }
         } else {
             let slide_last_idx = p.slides.len() - 1;
@@ -197,49 +641,137 @@ pub fn parse<'a>(
                     p.slides.push(Slide {
                         title: newt.clone(),
                         content: Vec::new(),
+                        fit: None,
+                        layout: None,
+                        header: true,
+                        footer: true,
+                        hidden: false,
                     });
                     last_title = newt.clone();
                 },
-                Item::BeginGrid(r) => _push(
-                    &mut p,
-                    Content::Grid(Grid {
-                        ratio: *r,
-                        first: Vec::new(),
-                        second: Vec::new(),
-                        _parser_col_adv: false,
-                    }),
-                ),
+                Item::BeginGrid(r) => _push(&mut p, Content::Grid(Grid::new(*r))),
                 Item::NextColumn => {
-                    let content_last_idx = p.slides[slide_last_idx].content.len() - 1;
-                    match p.slides[slide_last_idx].content[content_last_idx] {
-                        Content::Grid(Grid {
-                            ref mut _parser_col_adv,
-                            ..
-                        }) => {
-                            if *_parser_col_adv == true {
-                                eprintln!("Error: Spurious .COL directives");
+                    match p.slides[slide_last_idx].content.last_mut() {
+                        Some(Content::Grid(g)) if !g.closed => {
+                            if g.split {
+                                diag(diags, loc, "spurious-col", "Spurious .COL directive".to_string());
                             }
-                            *_parser_col_adv = true;
+                            g.split = true;
                         }
-                        _ => eprintln!("Error: Spurious .COL directives (no parent)"),
+                        _ => diag(diags, loc, "col-outside-grid", ".COL outside of a grid".to_string()),
+                    }
+                }
+                Item::EndGrid => {
+                    if let Some(Content::Grid(g)) = p.slides[slide_last_idx].content.last_mut() {
+                        g.closed = true;
+                    }
+                }
+                Item::Align(a) => _push(&mut p, Content::Align(*a)),
+                Item::Fit(f) => p.slides[slide_last_idx].fit = Some(*f),
+                Item::Header(None) => p.slides[slide_last_idx].header = false,
+                Item::Footer(None) => p.slides[slide_last_idx].footer = false,
+                Item::Hidden => p.slides[slide_last_idx].hidden = true,
+                Item::Layout(l) => {
+                    p.slides[slide_last_idx].layout = Some(*l);
+                    // Columns are split with .COL, as with an explicit grid.
+                    if *l == SlideLayout::TwoColumn {
+                        _push(&mut p, Content::Grid(Grid::new(50)));
                     }
                 }
-                Item::EndGrid => p.slides[slide_last_idx].content.push(Content::Dummy),
                 Item::Md(md) => _push(&mut p, Content::Md(md.clone())),
-                Item::Img(path) => _push(
-                    &mut p,
-                    Content::Img(match tcreator.load_texture(path) {
-                        Ok(t) => t,
-                        Err(s) => {
-                            eprintln!("Couldn't load image: {}", s);
-                            continue;
-                        }
-                    }),
-                ),
-                _ => eprintln!("Unexpected content: {:?}", item),
+                Item::Img(path) => {
+                    missing_image(path, loc, diags);
+                    _push(&mut p, Content::Img(path.clone()));
+                }
+                _ => diag(diags, loc, "config-after-slide", format!("{} after the first .SLD", item.name())),
             }
         }
     }
 
     p
 }
+
+// Images are only loaded along with the deck, but a missing one can be
+// pointed out here already.
+fn missing_image(path: &Path, loc: &Loc, diags: &mut Vec<Diag>) {
+    if !path.is_file() {
+        diag(diags, loc, "missing-image", format!("Couldn't load image: {}", path.display()));
+    }
+}
+
+// Markdown with nothing in it, e.g. blank lines between directives.
+pub fn is_blank(md: &markdown::mdast::Node) -> bool {
+    md.children().is_none_or(|c| c.is_empty())
+}
+
+// One word per line, with hyphens at the allowed break points, e.g.
+// "hy-phen-ation". Stored by the lowercased word, as character offsets.
+fn load_hyphens(hyphens: &mut HashMap<String, Vec<usize>>, dict: &str) {
+    for line in dict.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let mut word = String::new();
+        let mut breaks = Vec::new();
+        for ch in line.chars() {
+            if ch == '-' {
+                breaks.push(word.chars().count());
+            } else {
+                word.extend(ch.to_lowercase());
+            }
+        }
+        hyphens.insert(word, breaks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // Write files into a fresh directory and lex the first one.
+    fn lex_files(name: &str, files: &[(&str, &str)]) -> (Vec<(Loc, Item)>, Vec<Diag>) {
+        let dir = env::temp_dir().join(format!("eileda-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, text) in files {
+            fs::write(dir.join(file), text).unwrap();
+        }
+        let mut diags = Vec::new();
+        let items = lex_with(dir.join(files[0].0).to_str().unwrap(), &mut diags);
+        fs::remove_dir_all(&dir).unwrap();
+        (items, diags)
+    }
+
+    #[test]
+    fn template_import_cycle() {
+        let (items, diags) = lex_files("tpi", &[
+            ("a.eimd", ".TPI b.eimd\n.SLD @big Hello\nWorld\n"),
+            ("b.eimd", ".TPI a.eimd\n.TPL big\n.LAY big-statement\n{body}\n.END\n"),
+        ]);
+        assert!(diags.iter().any(|d| d.code == "include" && d.msg.starts_with("Import cycle")));
+        assert!(items.iter().any(|(_, i)| matches!(i, Item::Layout(SlideLayout::BigStatement))));
+    }
+
+    #[test]
+    fn template_self_import() {
+        let (_, diags) = lex_files("self", &[("a.eimd", ".TPI a.eimd\n.SLD A\n")]);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "include");
+    }
+
+    #[test]
+    fn col_as_first_item() {
+        let (items, mut diags) = lex_files("col", &[("a.eimd", ".SLD A\n.COL\nText\n")]);
+        let deck = parse_with(&items, &mut diags);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "col-outside-grid");
+        assert_eq!(deck.slides[0].content.len(), 1);
+    }
+
+    #[test]
+    fn at_titles_without_template() {
+        let (items, diags) = lex_files("at", &[("a.eimd", ".TPL big\n.LAY big-statement\n.END\n.SLD @rustlang on Mastodon\n.SLD @big\n")]);
+        assert!(diags.is_empty());
+        let deck = parse(&items);
+        assert_eq!(deck.slides[0].title.as_deref(), Some("@rustlang on Mastodon"));
+        assert_eq!(deck.slides[1].layout, Some(SlideLayout::BigStatement));
+    }
+}
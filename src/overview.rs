@@ -0,0 +1,151 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Texture, TextureCreator, WindowCanvas};
+use sdl2::video::WindowContext;
+
+use eileda::layout::{H, W};
+use eileda::slide::Presentation;
+use eileda::uf2;
+
+const COLS: usize = 5;
+const GAP: usize = 8;
+const THUMB_W: usize = (W - GAP * (COLS + 1)) / COLS;
+const THUMB_H: usize = THUMB_W * H / W;
+const LABEL_H: usize = 14;
+const ROW_H: usize = THUMB_H + LABEL_H + GAP;
+const ROWS: usize = (H - GAP) / ROW_H;
+
+const BG: Color = Color::RGB(0x23, 0x23, 0x34);
+const FG: Color = Color::RGB(220, 220, 200);
+const SEL: Color = Color::RGB(0xbb, 0x33, 0x33);
+const HIDDEN: Color = Color::RGBA(0x23, 0x23, 0x34, 160);
+
+// Every slide as a thumbnail, in a grid that scrolls by rows. Hidden slides
+// are included, dimmed, so that they can still be reached from here.
+pub struct Overview<'a> {
+    thumbs: Vec<Texture<'a>>,
+    pub sel: usize,
+    top: usize,
+}
+
+impl<'a> Overview<'a> {
+    // Slides are drawn full-size into a scratch target, which is then scaled
+    // down into each thumbnail, so that they look just like the real thing.
+    pub fn new(
+        p: &Presentation,
+        canvas: &mut WindowCanvas,
+        tcreator: &'a TextureCreator<WindowContext>,
+        cur: usize,
+    ) -> Result<Self, String> {
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "linear");
+        let scratch = tcreator.create_texture_target(None, (W * 2) as u32, (H * 2) as u32);
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
+        let mut scratch = scratch.map_err(|e| e.to_string())?;
+
+        let scale = canvas.scale();
+        let viewport = canvas.viewport();
+
+        let mut thumbs = Vec::new();
+        for i in 0..p.slides.len() {
+            canvas
+                .with_texture_canvas(&mut scratch, |c| {
+                    c.set_scale(2.0, 2.0).unwrap();
+                    p.draw(i, c);
+                })
+                .map_err(|e| e.to_string())?;
+
+            let mut thumb = tcreator
+                .create_texture_target(None, (THUMB_W * 2) as u32, (THUMB_H * 2) as u32)
+                .map_err(|e| e.to_string())?;
+            canvas
+                .with_texture_canvas(&mut thumb, |c| c.copy(&scratch, None, None).unwrap())
+                .map_err(|e| e.to_string())?;
+            thumbs.push(thumb);
+        }
+        canvas.set_scale(scale.0, scale.1)?;
+        canvas.set_viewport(viewport);
+
+        let mut ov = Overview { thumbs, sel: cur, top: 0 };
+        ov.select(cur);
+        Ok(ov)
+    }
+
+    fn rows(&self) -> usize {
+        self.thumbs.len().div_ceil(COLS)
+    }
+
+    // Select a slide, scrolling it into view.
+    pub fn select(&mut self, slide: usize) {
+        self.sel = slide.min(self.thumbs.len().saturating_sub(1));
+        let row = self.sel / COLS;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + ROWS {
+            self.top = row + 1 - ROWS;
+        }
+    }
+
+    // Move the selection by a number of columns and rows.
+    pub fn step(&mut self, dx: isize, dy: isize) {
+        let to = self.sel as isize + dx + dy * COLS as isize;
+        if to >= 0 && (to as usize) < self.thumbs.len() {
+            self.select(to as usize);
+        }
+    }
+
+    pub fn scroll(&mut self, rows: isize) {
+        let max = self.rows().saturating_sub(ROWS) as isize;
+        self.top = (self.top as isize + rows).clamp(0, max.max(0)) as usize;
+    }
+
+    fn cell(&self, slide: usize) -> Option<(usize, usize)> {
+        let row = (slide / COLS).checked_sub(self.top)?;
+        if row >= ROWS {
+            return None;
+        }
+        let col = slide % COLS;
+        Some((GAP + col * (THUMB_W + GAP), GAP + row * ROW_H))
+    }
+
+    // Slide under a point, in canvas coordinates.
+    pub fn hit(&self, x: usize, y: usize) -> Option<usize> {
+        (0..self.thumbs.len()).find(|&i| match self.cell(i) {
+            Some((cx, cy)) => (cx..cx + THUMB_W).contains(&x) && (cy..cy + THUMB_H).contains(&y),
+            None => false,
+        })
+    }
+
+    pub fn draw(&self, p: &Presentation, cur: usize, canvas: &mut WindowCanvas) {
+        canvas.set_draw_color(BG);
+        canvas.clear();
+
+        for (i, thumb) in self.thumbs.iter().enumerate() {
+            let (x, y) = match self.cell(i) {
+                Some(c) => c,
+                None => continue,
+            };
+            let dst = Rect::new(x as _, y as _, THUMB_W as _, THUMB_H as _);
+            canvas.copy(thumb, None, Some(dst)).unwrap();
+
+            if p.slides[i].hidden {
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(HIDDEN);
+                canvas.fill_rect(dst).unwrap();
+                canvas.set_blend_mode(BlendMode::None);
+            }
+
+            let label = if p.slides[i].hidden { format!("{} (hidden)", i + 1) } else { format!("{}", i + 1) };
+            canvas.set_draw_color(if i == cur { SEL } else { FG });
+            let b = uf2::Bounds::at(x, y + THUMB_H + 2);
+            uf2::draw(canvas, &*uf2::FONT_GENEVA12, &uf2::Style::default(), b, &label);
+
+            if i == self.sel {
+                canvas.set_draw_color(SEL);
+                for d in 1..=2 {
+                    let r = Rect::new(x as i32 - d, y as i32 - d, (THUMB_W as i32 + d * 2) as _, (THUMB_H as i32 + d * 2) as _);
+                    canvas.draw_rect(r).unwrap();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+
+use eileda::layout::{H, W};
+use eileda::slide::{Content, Presentation, Slide};
+use eileda::uf2;
+
+const BAR_H: usize = 20;
+const BAR_PAD: usize = 4;
+
+const BG: Color = Color::RGB(0x23, 0x23, 0x34);
+const FG: Color = Color::RGB(220, 220, 200);
+const MISS: Color = Color::RGB(0xbb, 0x33, 0x33);
+
+// Incremental search over slide titles and text. Every word of the query has
+// to appear in the slide, ignoring case. Hidden slides are searched too, so
+// that backup slides can be found during questions.
+#[derive(Default)]
+pub struct Search {
+    pub query: String,
+    matches: Vec<usize>,
+    sel: usize,
+}
+
+fn content_text(content: &[Content], out: &mut String) {
+    for c in content {
+        match c {
+            Content::Md(md) => {
+                out.push_str(&md.to_string());
+                out.push(' ');
+            }
+            Content::Grid(g) => {
+                content_text(&g.first, out);
+                content_text(&g.second, out);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn slide_text(slide: &Slide) -> String {
+    let mut out = slide.title.clone().unwrap_or_default();
+    out.push(' ');
+    content_text(&slide.content, &mut out);
+    out.to_lowercase()
+}
+
+impl Search {
+    pub fn update(&mut self, p: &Presentation) {
+        let words = self.query.to_lowercase();
+        let words = words.split_whitespace().collect::<Vec<_>>();
+
+        self.matches = if words.is_empty() {
+            Vec::new()
+        } else {
+            (0..p.slides.len())
+                .filter(|&i| {
+                    let text = slide_text(&p.slides[i]);
+                    words.iter().all(|w| text.contains(w))
+                })
+                .collect()
+        };
+        self.sel = 0;
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.matches.get(self.sel).copied()
+    }
+
+    // Cycle through the matches.
+    pub fn step(&mut self, d: isize) {
+        if !self.matches.is_empty() {
+            let n = self.matches.len() as isize;
+            self.sel = (self.sel as isize + d).rem_euclid(n) as usize;
+        }
+    }
+
+    // The current match, or the slide the search started from, with the
+    // prompt along the bottom.
+    pub fn draw(&self, p: &Presentation, cur: usize, canvas: &mut WindowCanvas) {
+        let slide = self.current().unwrap_or(cur);
+        p.draw(slide, canvas);
+
+        let y = H - BAR_H;
+        canvas.set_draw_color(BG);
+        canvas.fill_rect(Rect::new(0, y as _, W as _, BAR_H as _)).unwrap();
+
+        let font = &*uf2::FONT_GENEVA12;
+        let sty = uf2::Style::default();
+        let ty = y + (BAR_H - sty.line_height(font)) / 2;
+
+        let status = match self.current() {
+            Some(i) => format!(
+                "{}/{}: {} {}",
+                self.sel + 1,
+                self.matches.len(),
+                i + 1,
+                p.slides[i].title.as_deref().unwrap_or(""),
+            ),
+            None if self.query.trim().is_empty() => String::new(),
+            None => "No match".to_string(),
+        };
+        let sw = uf2::measure(font, &sty, &status);
+
+        canvas.set_draw_color(FG);
+        let prompt = format!("/{}_", self.query);
+        uf2::draw(canvas, font, &sty, uf2::Bounds::at(BAR_PAD, ty), &prompt);
+
+        canvas.set_draw_color(if self.current().is_some() { FG } else { MISS });
+        let sx = W.saturating_sub(BAR_PAD + sw);
+        uf2::draw(canvas, font, &sty, uf2::Bounds::at(sx, ty), &status);
+    }
+}
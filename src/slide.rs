@@ -1,5 +1,8 @@
-use std::cell::RefCell;
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
+use crate::layout::{Kind, Metrics, Node, UfxMetrics};
 use crate::uf2;
 
 use bitflags::bitflags;
@@ -12,27 +15,89 @@ use sdl2::render::Texture;
 use sdl2::render::WindowCanvas;
 use sdl2::render::TextureCreator;
 use sdl2::video::WindowContext;
+use sdl2::image::LoadTexture;
+use sdl2::rwops::RWops;
+use sdl2::ttf::Sdl2TtfContext;
 
-const PAR_PAD: usize = 12;
-const LST_MAR: usize = 8;
-const IMG_SPC: usize = 12;
-const COL_SPC: usize = 12;
-
-pub struct Margin<'a> {
-    pub image: Texture<'a>,
+pub struct Margin {
+    pub image: PathBuf,
     pub middle: usize,
 }
 
-pub struct GlobalConfig<'a> {
+pub struct GlobalConfig {
     pub padding: usize,
-    pub margin: Option<Margin<'a>>,
+    pub margin: Option<Margin>,
     pub ttf: bool,
+    pub kerning: bool,
+    pub align: Align,
+    pub tracking: usize,
+    pub hyphens: HashMap<String, Vec<usize>>,
+    pub fit: FitMode,
+    pub header: Option<Vec<String>>,
+    pub footer: Option<Vec<String>>,
+    pub show_hidden: bool,
+    pub theme: Theme,
+}
+
+impl Default for GlobalConfig {
+    fn default() -> Self {
+        GlobalConfig {
+            padding: 16,
+            margin: None,
+            ttf: false,
+            kerning: true,
+            align: Align::Left,
+            tracking: 0,
+            hyphens: HashMap::new(),
+            fit: FitMode::Off,
+            header: None,
+            footer: None,
+            show_hidden: false,
+            theme: Theme::Light,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Meta {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub event: Option<String>,
+}
+
+// A parsed deck, before anything is loaded for a renderer. Images are only
+// paths at this point.
+#[derive(Default)]
+pub struct Deck {
+    pub config: GlobalConfig,
+    pub meta: Meta,
+    pub slides: Vec<Slide>,
+}
+
+// A deck loaded for a window: its images as textures and the TTF fonts.
 pub struct Presentation<'a> {
     pub tcreator: &'a TextureCreator<WindowContext>,
-    pub config: GlobalConfig<'a>,
-    pub slides: Vec<Slide<'a>>,
+    pub config: GlobalConfig,
+    pub meta: Meta,
+    pub slides: Vec<Slide>,
+    pub images: HashMap<PathBuf, Texture<'a>>,
 
     pub f_bold_68: RefCell<Sdl2Font<'a, 'a>>,
     pub f_norm_24: RefCell<Sdl2Font<'a, 'a>>,
@@ -41,448 +106,313 @@ pub struct Presentation<'a> {
     pub f_both_24: RefCell<Sdl2Font<'a, 'a>>,
 }
 
-pub struct Slide<'a> {
+pub struct Slide {
     pub title: Option<String>,
-    pub content: Vec<Content<'a>>,
+    pub content: Vec<Content>,
+    pub fit: Option<FitMode>,
+    pub layout: Option<SlideLayout>,
+    pub header: bool,
+    pub footer: bool,
+    pub hidden: bool,
 }
 
-pub enum Content<'a> {
-    Grid(Grid<'a>),
-    Md(markdown::mdast::Node),
-    Img(Texture<'a>),
-    Dummy,
+// How a slide's title and content are arranged.
+//
+// - Content: title at the top, content stacked below it.
+// - Title: title and content centered, e.g. subtitle and author.
+// - Section: title above a rule, centered vertically.
+// - TwoColumn: like Content, but the content is an even grid.
+// - BigStatement: content only, centered and at twice the size.
+// - ImageFull: images fill the whole window, the title becomes a caption.
+//
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum SlideLayout {
+    #[default]
+    Content,
+    Title,
+    Section,
+    TwoColumn,
+    BigStatement,
+    ImageFull,
 }
 
-pub struct Grid<'a> {
-    pub ratio: usize,
-    pub first: Vec<Content<'a>>,
-    pub second: Vec<Content<'a>>,
-
-    pub _parser_col_adv: bool,
+// What to do with slides that don't fit: nothing, tighten the spacing
+// between blocks, or tighten the spacing and then shrink the text.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum FitMode {
+    #[default]
+    Off,
+    Spacing,
+    Shrink,
 }
 
-impl<'a> Presentation<'a> {
-    pub fn draw(&self, slide: usize, canvas: &mut WindowCanvas) {
-        const S: usize = 2;
-        const W: usize = 960 / S;
-        const H: usize = 840 / S;
+pub enum Content {
+    Grid(Grid),
+    Md(markdown::mdast::Node),
+    Img(PathBuf),
+    Align(Align),
+}
 
-        canvas.set_draw_color(Color::RGB(255, 255, 255));
-        canvas.clear();
-        canvas.set_draw_color(Color::RGB(0, 0, 0));
+pub struct Grid {
+    pub ratio: usize,
+    pub first: Vec<Content>,
+    pub second: Vec<Content>,
 
-        let mut lx = 0;
-        let mut ex = W;
-        let mut sy = 0;
-        let mut ey = H;
-
-        if let Some(ref margin) = self.config.margin {
-            let (iw, ih) = (
-                margin.image.query().width as u32,
-                margin.image.query().height as u32,
-            ); // image w/h
-            let x_pad = (ex - lx).saturating_sub(margin.middle / S) / 2;
-            ex -= x_pad;
-            lx += x_pad;
-
-            let (lmw, lmh) = (lx as u32, H as u32); // left margin width/height
-            canvas.copy(
-                &margin.image,
-                Some(Rect::new(0, 0, lmw.min(iw), lmh.min(ih) as _)),
-                Some(Rect::new(0, 0, lmw, lmh)),
-            ).unwrap();
-
-            let (rmw, rmh) = ((W - ex) as u32, H as u32); // right margin width/height
-            let img_start = iw.saturating_sub(rmw) as i32;
-            canvas.copy(
-                &margin.image,
-                Some(Rect::new(img_start, 0, rmw.min(iw), rmh.min(ih))),
-                Some(Rect::new(ex as _, 0, rmw, rmh)),
-            ).unwrap();
-        }
+    // Whether a .COL moved on to the second column, and whether a .GRD end
+    // closed the grid, after which content goes below it.
+    pub(crate) split: bool,
+    pub(crate) closed: bool,
+}
 
-        if self.config.padding > 0 {
-            lx += self.config.padding;
-            ex -= self.config.padding;
-            sy += self.config.padding;
-            ey -= self.config.padding;
+impl Grid {
+    pub(crate) fn new(ratio: usize) -> Grid {
+        Grid {
+            ratio,
+            first: Vec::new(),
+            second: Vec::new(),
+            split: false,
+            closed: false,
         }
+    }
 
-        if let Some(ref title) = self.slides[slide].title {
-            let (_, dy) = draw_text(self, canvas, lx, ex, lx, sy, DrawFl::TITLE, title);
-            sy += dy + (PAR_PAD * 3);
+    // Column that content goes into while the grid is open.
+    pub(crate) fn column(&mut self) -> &mut Vec<Content> {
+        if self.split {
+            &mut self.second
+        } else {
+            &mut self.first
         }
-
-        draw_content(self, canvas, &self.slides[slide].content, lx, ex, sy, ey);
     }
 }
 
-bitflags! {
-    #[derive(PartialEq, Copy, Clone)]
-    pub struct DrawFl: u16 {
-        const NONE = 0b0000;
-        const BOLD = 0b0001;
-        const EMPH = 0b0010;
-        const HEAD = 0b0100;
-        const TITLE = 0b1000;
+// Every image a deck refers to, in the margin or on its slides.
+fn image_paths<'d>(content: &'d [Content], out: &mut Vec<&'d PathBuf>) {
+    for c in content {
+        match c {
+            Content::Img(path) => out.push(path),
+            Content::Grid(g) => {
+                image_paths(&g.first, out);
+                image_paths(&g.second, out);
+            }
+            _ => {}
+        }
     }
 }
 
-fn draw_content<'a>(
-    p: &Presentation,
-    canvas: &mut WindowCanvas,
-    content: &Vec<Content<'a>>,
-    lx: usize,
-    ex: usize,
-    sy: usize,
-    ey: usize,
-) -> usize {
-    let mut y = sy;
-
-    for item in content {
-        match item {
-            Content::Dummy => (),
-            Content::Grid(Grid {
-                ratio,
-                first,
-                second,
-                ..
-            }) => {
-                let bx = lx + ((ex - lx) * ratio / 100);
-                let y1 = draw_content(p, canvas, first, lx, bx, y, ey);
-                let y2 = draw_content(p, canvas, second, bx + COL_SPC, ex, y, ey);
-                y = y1.max(y2);
-            }
-            Content::Img(t) => {
-                let (iw, ih) = (t.query().width, t.query().height); // image w/h
-                let (mw, mh) = ((ex - lx) as _, (ey - y) as _); // max w/h
-                let (cw, ch); // calculated w/h
-
-                // Scale image down into boundaries, depending on which dimension
-                // (height/width) is over bounds most
-                if iw < mw && ih < mh {
-                    cw = iw;
-                    ch = ih;
-                } else if iw.saturating_sub(mw) > ih.saturating_sub(mh) {
-                    cw = mw;
-                    ch = (ih as f32 * (mw as f32 / iw as f32)) as u32;
-                } else {
-                    ch = mh;
-                    cw = (iw as f32 * (mh as f32 / ih as f32)) as u32;
+impl<'a> Presentation<'a> {
+    // Images that can't be loaded are left out of the slides. Missing files
+    // have already been reported by the parser.
+    pub fn load(
+        deck: Deck,
+        tcreator: &'a TextureCreator<WindowContext>,
+        fcreator: &'a Sdl2TtfContext,
+    ) -> Presentation<'a> {
+        macro_rules! embed_ttf {
+            ($path:literal, $sz:literal) => {
+                {
+                    let data = include_bytes!($path);
+                    let rwops = Box::new(RWops::from_bytes(data).unwrap());
+                    let mut font = fcreator.load_font_from_rwops(*rwops, $sz).unwrap();
+                    font.set_kerning(deck.config.kerning);
+                    RefCell::new(font)
                 }
+            }
+        }
 
-                let dst = Rect::new(lx as _, y as _, cw, ch);
-                canvas.copy(t, None, Some(dst)).unwrap();
+        let mut paths = Vec::new();
+        if let Some(m) = &deck.config.margin {
+            paths.push(&m.image);
+        }
+        for s in &deck.slides {
+            image_paths(&s.content, &mut paths);
+        }
 
-                y += ih as usize + IMG_SPC;
+        let mut images = HashMap::new();
+        for path in paths {
+            if images.contains_key(path) {
+                continue;
             }
-            Content::Md(md) => {
-                let (_, ny) = draw_md(p, canvas, &md, lx, ex, lx, y, DrawFl::NONE);
-                y = ny;
+            match tcreator.load_texture(path) {
+                Ok(t) => {
+                    images.insert(path.clone(), t);
+                }
+                Err(e) if path.exists() => eprintln!("Couldn't load image {}: {}", path.display(), e),
+                Err(_) => {}
             }
         }
+
+        Presentation {
+            tcreator,
+            f_bold_68: embed_ttf!("../assets/ttf/Inter-Bold.otf", 68),
+            f_norm_24: embed_ttf!("../assets/ttf/Inter-Regular.otf", 24),
+            f_bold_24: embed_ttf!("../assets/ttf/Inter-Bold.otf", 24),
+            f_emph_24: embed_ttf!("../assets/ttf/Inter-Italic.otf", 24),
+            f_both_24: embed_ttf!("../assets/ttf/Inter-BoldItalic.otf", 24),
+
+            config: deck.config,
+            meta: deck.meta,
+            slides: deck.slides,
+            images,
+        }
     }
 
-    y
-}
+    pub fn window_title(&self) -> String {
+        match (&self.meta.title, &self.meta.event) {
+            (Some(t), Some(e)) => format!("{} ({}) - eileda", t, e),
+            (Some(t), None) => format!("{} - eileda", t),
+            _ => "eileda".to_string(),
+        }
+    }
 
-fn draw_md(
-    p: &Presentation,
-    canvas: &mut WindowCanvas,
-    node: &markdown::mdast::Node,
-    lx: usize,
-    ex: usize,
-    sx: usize,
-    sy: usize,
-    fl: DrawFl,
-) -> (usize, usize) {
-    use markdown::mdast::Node as N;
-    use markdown::mdast::*;
-
-    let mut x = sx;
-    let mut y = sy;
-
-    let fnt = if fl.contains(DrawFl::BOLD) {
-        &*uf2::FONT_VENICE14
-    } else if fl.contains(DrawFl::HEAD) {
-        //&*uf2::FONT_NEWYORK14
-        &*uf2::FONT_TIMES15
-    } else if fl.contains(DrawFl::EMPH) {
-        &*uf2::FONT_ANGELES12
-        //&*uf2::FONT_CREAM12
-        //&*uf2::FONT_SHAVIAN12
-    } else {
-        &*uf2::FONT_GENEVA12
-    };
+    // Hidden slides are skipped when stepping through the deck, unless they
+    // are shown.
+    pub fn visible(&self, slide: usize) -> bool {
+        self.config.show_hidden || !self.slides[slide].hidden
+    }
 
-    let frect = |canvas: &mut WindowCanvas, x: usize, y: usize, w: usize, h: usize, c: u32| {
-        let (r, g, b) = (c >> 16, c >> 8 & 0xFF, c & 0xFF);
-        canvas.set_draw_color(Color::RGB(r as _, g as _, b as _));
-        canvas.fill_rect(Rect::new(x as _, y as _, w as _, h as _)).unwrap();
-        canvas.set_draw_color(Color::RGB(0, 0, 0));
-    };
+    pub fn first(&self) -> usize {
+        (0..self.slides.len()).find(|&i| self.visible(i)).unwrap_or(0)
+    }
+
+    pub fn last(&self) -> usize {
+        (0..self.slides.len()).rev().find(|&i| self.visible(i)).unwrap_or(0)
+    }
+
+    pub fn next(&self, cur: usize) -> usize {
+        (cur + 1..self.slides.len()).find(|&i| self.visible(i)).unwrap_or(cur)
+    }
+
+    pub fn prev(&self, cur: usize) -> usize {
+        (0..cur).rev().find(|&i| self.visible(i)).unwrap_or(cur)
+    }
 
-    #[allow(unused_variables)]
-    let drect = |canvas: &mut WindowCanvas, x: usize, y: usize, w: usize, h: usize| {
-        //canvas.set_draw_color(Color::RGB(186, 187, 186));
-        canvas.draw_rect(Rect::new(x as _, y as _, w as _, h as _)).unwrap();
+    pub fn draw(&self, slide: usize, canvas: &mut WindowCanvas) {
+        let tree = self.layout(slide);
+        paint(self, canvas, &tree);
         canvas.set_draw_color(Color::RGB(0, 0, 0));
-    };
+    }
+}
 
-    match node {
-        N::Root(Root { children, .. }) => {
-            for c in children {
-                let (nx, ny) = draw_md(p, canvas, c, lx, ex, x, y, fl);
-                x = nx;
-                y = ny;
-            }
-            x = sx;
-        }
-        N::Paragraph(Paragraph { children, .. }) => {
-            for c in children {
-                let (nx, ny) = draw_md(p, canvas, c, lx, ex, x, y, fl);
-                x = nx;
-                y = ny;
-            }
+bitflags! {
+    #[derive(PartialEq, Copy, Clone)]
+    pub struct DrawFl: u16 {
+        const NONE = 0b0000;
+        const BOLD = 0b0001;
+        const EMPH = 0b0010;
+        const HEAD = 0b0100;
+        const TITLE = 0b1000;
+    }
+}
 
-            x = sx;
-            y += 2 * 8;
-            y += PAR_PAD;
-        }
-        N::Heading(Heading {
-            children, depth: _, ..
-        }) => {
-            //y += PAR_PAD;
-
-            // STYLE 2
-            //const HEAD_HR_W: usize = 24;
-            //rect(canvas, x, y + 6, HEAD_HR_W, 3);
-            //x += HEAD_HR_W + 8;
-
-            // STYLE 1
-            //const HEAD_BOX_PAD_X: usize = 10;
-            //const HEAD_BOX_PAD_Y: usize = 8;
-            let (ox, oy) = (x, y);
-            // x += HEAD_BOX_PAD_X;
-            // y += HEAD_BOX_PAD_Y;
-
-            // STYLE 3
-            // for p in 0..10 {
-            //     frect(canvas, x, oy, 2, 16);
-            //     x += 6 - 6usize.saturating_sub(p / 1).max(1);
-            // }
-            // x += 6;
-
-            for c in children {
-                let (nx, ny) = draw_md(p, canvas, c, lx, ex, x, y, fl | DrawFl::HEAD);
-                x = nx;
-                y = ny;
-            }
+pub fn paint(p: &Presentation, canvas: &mut WindowCanvas, node: &Node) {
+    let dst = Rect::new(node.x as _, node.y as _, node.w as _, node.h as _);
 
-            // STYLE 4
-            frect(canvas, ox - 4, oy - 4, x - ox + 8, fnt.height + 8, 0x232334);
-            frect(canvas, ox - 4, oy + fnt.height + 4, ex - ox, 4, 0x232334);
-            (x, y) = (ox, oy);
-            canvas.set_draw_color(Color::RGB(220, 220, 200));
-            for c in children {
-                let (nx, ny) = draw_md(p, canvas, c, lx, ex, x, y, fl | DrawFl::HEAD);
-                x = nx;
-                y = ny;
-            }
-            canvas.set_draw_color(Color::RGB(0, 0, 0));
-
-            // STYLE 3
-            // x += 6;
-            // for p in 0..10 {
-            //     frect(canvas, x, oy, 2, 16);
-            //     x += 6usize.saturating_sub(p / 2).max(1);
-            // }
-
-            // STYLE 2
-            //y += PAR_PAD;
-            //drect(canvas, ox, oy, (x - ox) + (HEAD_BOX_PAD_X ), (y - oy) + (HEAD_BOX_PAD_Y * 1));
-
-            // STYLE 1
-            //rect(canvas, x + 8, y + 6, ex - x, 3);
-
-            x = sx;
-            y += fnt.height;
-            y += PAR_PAD;
-            y += PAR_PAD / 2;
-        }
-        N::Strong(Strong { children, .. }) => {
-            for c in children {
-                let (nx, ny) = draw_md(p, canvas, c, lx, ex, x, y, fl | DrawFl::BOLD);
-                x = nx;
-                y = ny;
-            }
+    match node.kind {
+        Kind::Group => (),
+        Kind::Fill(color) => {
+            canvas.set_draw_color(color);
+            canvas.fill_rect(dst).unwrap();
         }
-        N::Emphasis(Emphasis { children, .. }) => {
-            for c in children {
-                let (nx, ny) = draw_md(p, canvas, c, lx, ex, x, y, fl | DrawFl::EMPH);
-                x = nx;
-                y = ny;
+        Kind::Image { texture, src } => {
+            if let Some(texture) = texture {
+                canvas.copy(texture, src, Some(dst)).unwrap();
             }
         }
-        N::List(List {
-            children,
-            ordered,
-            start,
-            spread: _,
-            ..
-        }) => {
-            for (i, c) in children.iter().enumerate() {
-                let o;
-                if !*ordered {
-                    if p.config.ttf {
-                        let l = "• ";
-                        draw_text(p, canvas, lx + LST_MAR, ex, x + LST_MAR, y, DrawFl::NONE, l);
-                        o = LST_MAR + measure_text(p, DrawFl::NONE, &l);
-                    } else {
-                        uf2::draw_char(canvas, &*uf2::FONT_NEWYORK14, x + LST_MAR, y, 0xA5);
-                        o = LST_MAR
-                            + uf2::FONT_NEWYORK14.glyphs[0xA5].width as usize
-                            + uf2::FONT_NEWYORK14.glyphs[0x20].width as usize;
-                    }
-                } else {
-                    let l = &format!("{}) ", start.unwrap_or(1) as usize + i);
-                    draw_text(p, canvas, lx + LST_MAR, ex, x + LST_MAR, y, DrawFl::NONE, l);
-                    o = LST_MAR + measure_text(p, DrawFl::NONE, &l);
-                }
-                let (nx, ny) = draw_md(p, canvas, c, lx + o, ex, x + o, y, fl);
-                x = nx - o;
-                y = ny;
-            }
-            x = sx;
-            y += PAR_PAD;
+        Kind::Glyph { font, ch, color } => {
+            canvas.set_draw_color(color);
+            uf2::draw_char(canvas, font, &uf2::Style::default(), node.x, node.y, ch);
         }
-        N::ListItem(ListItem { children, .. }) => {
-            for c in children {
-                let (nx, ny) = draw_md(p, canvas, c, lx, ex, x, y, fl);
-                x = nx;
-                y = ny - PAR_PAD;
+        Kind::Text { fl, ref text, color, scale } => {
+            canvas.set_draw_color(color);
+            if p.config.ttf {
+                let font = ttf_font(p, fl);
+                let surf = font.render(text)
+                    .blended(color)
+                    .unwrap();
+                let text = surf.as_texture(p.tcreator).unwrap();
+                let (tw, th) = (text.query().width as f32, text.query().height as f32);
+                // Fonts are rasterized at twice the canvas size.
+                let (sx, sy) = canvas.scale();
+                let (tw, th) = ((tw * scale * sx / 2.0) as u32, (th * scale * sy / 2.0) as u32);
+                canvas.set_scale(1.0, 1.0).unwrap();
+                canvas.copy(
+                    &text,
+                    None,
+                    Some(Rect::new((node.x as f32 * sx) as i32, (node.y as f32 * sy) as i32, tw, th))
+                ).unwrap();
+                canvas.set_scale(sx, sy).unwrap();
+            } else {
+                let (fnt, sty) = ufx_font(fl, p.config.tracking);
+                let sty = sty.scaled(scale as usize);
+                uf2::draw(canvas, fnt, &sty, uf2::Bounds::at(node.x, node.y), text);
             }
         }
-        N::BlockQuote(BlockQuote { children, .. }) => {
-            let oldy = sy;
-            for c in children {
-                let (nx, ny) = draw_md(p, canvas, c, lx + 10, ex, x + 10, y, fl);
-                x = nx;
-                y = ny;
-            }
-            x = lx;
-            frect(canvas, lx, oldy, 4, y - oldy - PAR_PAD, 0xbabbba);
-        }
-        N::Text(Text { value, .. }) => {
-            let (nx, ny) = draw_text(p, canvas, lx, ex, x, y, fl, &value);
-            x = nx;
-            y = ny;
-        }
-        n => println!("Node not implemented: {:?}", n),
     }
 
-    (x, y)
+    for child in &node.children {
+        paint(p, canvas, child);
+    }
 }
 
-fn draw_text(
-    p: &Presentation,
-    canvas: &mut WindowCanvas,
-    lx: usize,
-    ex: usize,
-    sx: usize,
-    sy: usize,
-    fl: DrawFl,
-    value: &str,
-) -> (usize, usize)
-{
-    if p.config.ttf {
-        let font = if fl.contains(DrawFl::BOLD | DrawFl::EMPH) {
-            p.f_both_24.borrow_mut()
-        } else if fl.contains(DrawFl::BOLD) || fl.contains(DrawFl::HEAD) {
-            p.f_bold_24.borrow_mut()
-        } else if fl.contains(DrawFl::TITLE) {
-            p.f_bold_68.borrow_mut()
-        } else if fl.contains(DrawFl::EMPH) {
-            p.f_emph_24.borrow_mut()
+impl Metrics for Presentation<'_> {
+    fn measure(&self, fl: DrawFl, value: &str) -> usize {
+        if self.config.ttf {
+            (ttf_font(self, fl).size_of(value).unwrap().0 as usize) / 2
         } else {
-            p.f_norm_24.borrow_mut()
-        };
-
-        let mut x = sx;
-        let mut y = sy;
-
-        for group in value.split_inclusive(&[' ', '\n']) {
-            if x + (font.size_of(group).unwrap().0 as usize) / 2 >= ex {
-                y += (font.recommended_line_spacing() as usize) / 2;
-                x = lx;
-            }
-
-            let grp = group.to_string().clone().replace("\n", " ");
-            let surf = font.render(&grp)
-                .blended(canvas.draw_color())
-                .unwrap();
-            let text = surf.as_texture(p.tcreator).unwrap();
-            let (tw, th) = (text.query().width as usize, text.query().height as usize);
-            canvas.set_scale(1.0, 1.0).unwrap();
-            canvas.copy(
-                &text,
-                None,
-                Some(Rect::new((x * 2) as i32, (y * 2) as i32, tw as _, th as _))
-            ).unwrap();
-            canvas.set_scale(2.0, 2.0).unwrap();
-            x += (tw as f32 / 2.0).round() as usize;
+            UfxMetrics { tracking: self.config.tracking }.measure(fl, value)
         }
+    }
 
-        (x, y)
-    } else {
-        if fl.contains(DrawFl::TITLE) {
-            return uf2::draw(canvas, &*uf2::FONT_NEWYORK34, lx, ex, sx, sy, &value);
+    fn line_height(&self, fl: DrawFl) -> usize {
+        if self.config.ttf {
+            (ttf_font(self, fl).recommended_line_spacing() as usize) / 2
+        } else {
+            UfxMetrics { tracking: self.config.tracking }.line_height(fl)
         }
+    }
 
-        let fnt = if fl.contains(DrawFl::BOLD) {
-            &*uf2::FONT_VENICE14
-        } else if fl.contains(DrawFl::HEAD) {
-            &*uf2::FONT_TIMES15
-        } else if fl.contains(DrawFl::EMPH) {
-            &*uf2::FONT_ANGELES12
-        } else {
-            &*uf2::FONT_GENEVA12
-        };
+    fn image_size(&self, path: &Path) -> Option<(u32, u32)> {
+        let q = self.images.get(path)?.query();
+        Some((q.width, q.height))
+    }
+}
 
-        uf2::draw(canvas, fnt, lx, ex, sx, sy, &value)
+pub fn ttf_font<'p, 'a>(p: &'p Presentation<'a>, fl: DrawFl) -> RefMut<'p, Sdl2Font<'a, 'a>> {
+    if fl.contains(DrawFl::BOLD | DrawFl::EMPH) {
+        p.f_both_24.borrow_mut()
+    } else if fl.contains(DrawFl::BOLD) || fl.contains(DrawFl::HEAD) {
+        p.f_bold_24.borrow_mut()
+    } else if fl.contains(DrawFl::TITLE) {
+        p.f_bold_68.borrow_mut()
+    } else if fl.contains(DrawFl::EMPH) {
+        p.f_emph_24.borrow_mut()
+    } else {
+        p.f_norm_24.borrow_mut()
     }
 }
 
-fn measure_text(p: &Presentation, fl: DrawFl, value: &str) -> usize {
-    if p.config.ttf {
-        let font = if fl.contains(DrawFl::BOLD | DrawFl::EMPH) {
-            p.f_both_24.borrow_mut()
-        } else if fl.contains(DrawFl::BOLD) || fl.contains(DrawFl::HEAD) {
-            p.f_bold_24.borrow_mut()
-        } else if fl.contains(DrawFl::TITLE) {
-            p.f_bold_68.borrow_mut()
-        } else if fl.contains(DrawFl::EMPH) {
-            p.f_emph_24.borrow_mut()
-        } else {
-            p.f_norm_24.borrow_mut()
-        };
+// Bitmap face and synthetic style for a set of draw flags. Bold and emphasis
+// are synthesized on top of the body face where possible, so that a slide
+// sticks to one family.
+pub fn ufx_font(fl: DrawFl, tracking: usize) -> (&'static dyn uf2::Face, uf2::Style) {
+    use uf2::{Style, StyleFl};
 
-        (font.size_of(value).unwrap().0 as usize) / 2
+    let (fnt, sty): (&'static dyn uf2::Face, _) = if fl.contains(DrawFl::TITLE) {
+        (&*uf2::FONT_NEWYORK34, Style::default())
+    } else if fl.contains(DrawFl::HEAD) {
+        //(&*uf2::FONT_NEWYORK14, Style::default())
+        let sty = if fl.contains(DrawFl::BOLD) { StyleFl::BOLD } else { StyleFl::NONE };
+        (&*uf2::FONT_TIMES15, Style::new(sty))
+    } else if fl.contains(DrawFl::BOLD) {
+        //(&*uf2::FONT_VENICE14, Style::default())
+        (&*uf2::FONT_GENEVA12, Style::new(StyleFl::BOLD))
+    } else if fl.contains(DrawFl::EMPH) {
+        (&*uf2::FONT_ANGELES12, Style::default())
+        //(&*uf2::FONT_CREAM12, Style::default())
+        //(&*uf2::FONT_SHAVIAN12, Style::default())
     } else {
-        if fl.contains(DrawFl::TITLE) {
-            return uf2::measure(&*uf2::FONT_NEWYORK34, &value);
-        }
-
-        let fnt = if fl.contains(DrawFl::BOLD) {
-            &*uf2::FONT_VENICE14
-        } else if fl.contains(DrawFl::HEAD) {
-            &*uf2::FONT_TIMES15
-        } else if fl.contains(DrawFl::EMPH) {
-            &*uf2::FONT_ANGELES12
-        } else {
-            &*uf2::FONT_GENEVA12
-        };
+        (&*uf2::FONT_GENEVA12, Style::default())
+    };
 
-        uf2::measure(fnt, &value)
-    }
+    (fnt, Style { tracking, ..sty })
 }
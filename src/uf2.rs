@@ -1,9 +1,11 @@
 use std::sync::LazyLock;
 
+use bitflags::bitflags;
 use bitmaps::Bitmap;
 use paste::paste;
-use sdl2::rect::Point;
-use sdl2::render::WindowCanvas;
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Canvas, RenderTarget};
 
 macro_rules! fonts {
     ($($name:ident := @sz $sz:literal @h $h:literal $p:literal),*,) => {
@@ -12,6 +14,13 @@ macro_rules! fonts {
             pub static [<FONT_ $name>]: LazyLock<Ufx<$sz>> =
                 LazyLock::new(|| parse::<$sz>(include_bytes!($p), $h));
         })*
+
+        // Every registered face, by name, for tooling that doesn't know the
+        // sprite count in advance.
+        pub static FONTS: LazyLock<Vec<(&'static str, &'static dyn Face)>> =
+            LazyLock::new(|| vec![$(paste! {
+                (stringify!($name), &*[<FONT_ $name>] as &dyn Face)
+            }),*]);
     };
 }
 
@@ -57,6 +66,46 @@ impl<const XS: usize> Default for UfGlyph<XS> {
     }
 }
 
+pub trait Face: Sync {
+    fn height(&self) -> usize;
+    fn sprites(&self) -> usize;
+    fn width(&self, ch: u8) -> usize;
+    fn pixel(&self, ch: u8, x: usize, y: usize) -> bool;
+
+    // Side of the square glyph cell, in pixels.
+    fn cell(&self) -> usize {
+        (self.sprites() as f64).sqrt() as usize * 8
+    }
+
+    fn coverage(&self) -> usize {
+        (0..=255).filter(|ch| self.width(*ch) > 0).count()
+    }
+}
+
+impl<const XS: usize> Face for Ufx<XS> {
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn sprites(&self) -> usize {
+        XS
+    }
+
+    fn width(&self, ch: u8) -> usize {
+        self.glyphs[ch as usize].width as usize
+    }
+
+    fn pixel(&self, ch: u8, x: usize, y: usize) -> bool {
+        #[allow(non_snake_case)]
+        let X = (XS as f64).sqrt() as usize;
+
+        let glyph = &self.glyphs[ch as usize];
+        let sprite = ((x / 8) * X) + ((y / 8) % X);
+        let pixel = ((y & 7) * 8) + (7 - (x & 7));
+        glyph.inner[sprite].raw.get(pixel as _)
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Icn {
     pub raw: Bitmap<64>,
@@ -75,7 +124,7 @@ pub fn parse<const XS: usize>(bytes: &[u8], pxheight: usize) -> Ufx<XS> {
 
     for (i, byte) in bytes.iter().enumerate() {
         match i {
-            0..255 => ufx.glyphs[i].width = *byte,
+            0..256 => ufx.glyphs[i].width = *byte,
             _ => {
                 ufx.glyphs[g_i].inner[s_i].raw.as_mut()[b_i] = *byte;
                 b_i += 1;
@@ -97,71 +146,242 @@ pub fn parse<const XS: usize>(bytes: &[u8], pxheight: usize) -> Ufx<XS> {
     ufx
 }
 
-pub fn draw_char<const XS: usize>(
-    canvas: &mut WindowCanvas,
-    font: &Ufx<XS>,
+bitflags! {
+    #[derive(PartialEq, Copy, Clone, Debug, Default)]
+    pub struct StyleFl: u8 {
+        const NONE = 0b0000;
+        const BOLD = 0b0001;
+        const UNDERLINE = 0b0010;
+        const OUTLINE = 0b0100;
+        const SHADOW = 0b1000;
+    }
+}
+
+// Synthetic styling for bitmap faces, which otherwise only exist at their
+// native size and weight.
+//
+// - scale: integer scale factor, each font pixel is drawn as a scale*scale block.
+// - BOLD: double-strike, glyph is drawn again one (scaled) pixel to the right.
+// - UNDERLINE: rule along the bottom row of the line box.
+// - OUTLINE/SHADOW: glyph is first drawn in `effect` around/behind itself.
+// - tracking: extra space after each glyph, in font pixels.
+//
+#[derive(Copy, Clone, Debug)]
+pub struct Style {
+    pub scale: usize,
+    pub fl: StyleFl,
+    pub effect: Color,
+    pub tracking: usize,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            fl: StyleFl::NONE,
+            effect: Color::RGB(186, 187, 186),
+            tracking: 0,
+        }
+    }
+}
+
+impl Style {
+    pub fn new(fl: StyleFl) -> Self {
+        Self { fl, ..Default::default() }
+    }
+
+    pub fn scaled(self, scale: usize) -> Self {
+        Self { scale: scale.max(1), ..self }
+    }
+
+    pub fn line_height<F: Face + ?Sized>(&self, font: &F) -> usize {
+        font.cell() * self.scale
+    }
+
+    pub fn advance<F: Face + ?Sized>(&self, font: &F, ch: u8) -> usize {
+        let w = font.width(ch);
+        if w == 0 {
+            return 0;
+        }
+        let bold = if self.fl.contains(StyleFl::BOLD) { 1 } else { 0 };
+        (w + bold + self.tracking) * self.scale
+    }
+}
+
+fn strike_char<F: Face + ?Sized, T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    font: &F,
+    scale: usize,
+    sx: i32,
+    sy: i32,
+    ch: u8,
+) {
+    for x in 0..font.width(ch) {
+        for y in 0..font.cell() {
+            if !font.pixel(ch, x, y) {
+                continue;
+            }
+
+            let (px, py) = (sx + (x * scale) as i32, sy + (y * scale) as i32);
+            if scale == 1 {
+                canvas.draw_point(Point::new(px, py)).unwrap();
+            } else {
+                canvas.fill_rect(Rect::new(px, py, scale as _, scale as _)).unwrap();
+            }
+        }
+    }
+}
+
+// Inverse of parse(): 256 width bytes, followed by each glyph's sprites in
+// column-major order, one byte per 8px row, leftmost pixel in the MSB.
+pub fn serialize<F: Face + ?Sized>(font: &F) -> Vec<u8> {
+    #[allow(non_snake_case)]
+    let X = font.cell() / 8;
+
+    let mut bytes = Vec::with_capacity(256 + 256 * font.sprites() * 8);
+    bytes.extend((0..=255).map(|ch| font.width(ch).min(255) as u8));
+
+    for ch in 0..=255 {
+        for s in 0..font.sprites() {
+            let (sx, sy) = ((s / X) * 8, (s % X) * 8);
+            for y in sy..sy + 8 {
+                let row = (0..8).fold(0u8, |b, x| {
+                    b | ((font.pixel(ch, sx + x, y) as u8) << (7 - x))
+                });
+                bytes.push(row);
+            }
+        }
+    }
+
+    bytes
+}
+
+pub fn draw_char<F: Face + ?Sized, T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    font: &F,
+    style: &Style,
     sx: usize,
     sy: usize,
     ch: u8,
 ) {
-    #[allow(non_snake_case)]
-    let X = (XS as f64).sqrt() as usize;
-
-    let glyph: UfGlyph<XS> = font.glyphs[ch as usize];
-
-    for x in 0..(glyph.width as usize) {
-        for y in 0..(X * 8) {
-            let sprite = ((x / 8) * X) + ((y / 8) % X);
-            let pixel = ((y & 7) * 8) + (7 - (x & 7));
-            if glyph.inner[sprite].raw.get(pixel as _) {
-                canvas
-                    .draw_point(Point::new(sx as i32 + (x as i32), sy as i32 + (y as i32)))
-                    .unwrap();
+    let s = style.scale as i32;
+    let (sx, sy) = (sx as i32, sy as i32);
+    let strikes: &[i32] = if style.fl.contains(StyleFl::BOLD) { &[0, s] } else { &[0] };
+
+    if style.fl.intersects(StyleFl::OUTLINE | StyleFl::SHADOW) {
+        let color = canvas.draw_color();
+        canvas.set_draw_color(style.effect);
+
+        if style.fl.contains(StyleFl::SHADOW) {
+            for b in strikes {
+                strike_char(canvas, font, style.scale, sx + b + s, sy + s, ch);
             }
         }
+
+        if style.fl.contains(StyleFl::OUTLINE) {
+            for (dx, dy) in [(-s, -s), (0, -s), (s, -s), (-s, 0), (s, 0), (-s, s), (0, s), (s, s)] {
+                for b in strikes {
+                    strike_char(canvas, font, style.scale, sx + b + dx, sy + dy, ch);
+                }
+            }
+        }
+
+        canvas.set_draw_color(color);
+    }
+
+    for b in strikes {
+        strike_char(canvas, font, style.scale, sx + b, sy, ch);
     }
 }
 
-pub fn measure<const XS: usize>(font: &Ufx<XS>, text: &str) -> usize {
+pub fn measure<F: Face + ?Sized>(font: &F, style: &Style, text: &str) -> usize {
     text.as_bytes()
-        .into_iter()
-        .fold(0, |w, b| w + font.glyphs[*b as usize].width as usize)
+        .iter()
+        .fold(0, |w, b| w + style.advance(font, *b))
 }
 
-pub fn draw<const XS: usize>(
-    canvas: &mut WindowCanvas,
-    font: &Ufx<XS>,
-    lx: usize,
-    ex: usize,
-    sx: usize,
-    sy: usize,
+// Where text is drawn: it starts at x, y and wraps back to lx before it
+// reaches ex. Words wider than lx..ex are broken where they reach it.
+#[derive(Copy, Clone, Debug)]
+pub struct Bounds {
+    pub lx: usize,
+    pub ex: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Bounds {
+    // A single line, that never wraps.
+    pub fn at(x: usize, y: usize) -> Self {
+        Self { lx: x, ex: usize::MAX, x, y }
+    }
+}
+
+pub fn draw<F: Face + ?Sized, T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    font: &F,
+    style: &Style,
+    b: Bounds,
     s: &str,
 ) -> (usize, usize) {
-    #[allow(non_snake_case)]
-    let X = (XS as f64).sqrt() as usize;
+    let lh = style.line_height(font);
+    let (lx, ex) = (b.lx, b.ex);
+
+    let mut x = b.x;
+    let mut y = b.y;
 
-    let mut x = sx;
-    let mut y = sy;
+    let underline = |canvas: &mut Canvas<T>, ox: usize, x: usize, y: usize| {
+        if style.fl.contains(StyleFl::UNDERLINE) && x > ox {
+            let uy = y + font.height() * style.scale - style.scale;
+            canvas
+                .fill_rect(Rect::new(ox as _, uy as _, (x - ox) as _, style.scale as _))
+                .unwrap();
+        }
+    };
 
     for group in s.split_inclusive(&[' ', '\n']) {
-        if x + measure(font, group) >= ex {
-            y += 8 * X;
+        if x + measure(font, style, group) >= ex {
+            y += lh;
             x = lx;
         }
 
+        let mut ox = x;
+
         for ch in group.as_bytes() {
-            if *ch == b'\n' {
-                // y += 8 * X;
-                // x = lx;
-                draw_char(canvas, font, x, y, b' ');
-                x += font.glyphs[b' ' as usize].width as usize;
-                continue;
+            let ch = if *ch == b'\n' { b' ' } else { *ch };
+
+            let adv = style.advance(font, ch);
+            if ch != b' ' && x > lx && x + adv >= ex {
+                underline(canvas, ox, x, y);
+                y += lh;
+                x = lx;
+                ox = x;
             }
 
-            draw_char(canvas, font, x, y, *ch);
-            x += font.glyphs[*ch as usize].width as usize;
+            draw_char(canvas, font, style, x, y, ch);
+            x += adv;
         }
+
+        underline(canvas, ox, x, y);
     }
 
     (x, y)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_round_trips() {
+        for (name, font) in [("geneva12", &*FONT_GENEVA12), ("chicago12", &*FONT_CHICAGO12)] {
+            let back = parse::<4>(&serialize(font), font.height());
+            for ch in 0..=255u8 {
+                assert_eq!(back.width(ch), font.width(ch), "{} {}", name, ch);
+                for (x, y) in (0..font.cell()).flat_map(|x| (0..font.cell()).map(move |y| (x, y))) {
+                    assert_eq!(back.pixel(ch, x, y), font.pixel(ch, x, y), "{} {} {},{}", name, ch, x, y);
+                }
+            }
+        }
+    }
+}
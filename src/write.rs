@@ -0,0 +1,474 @@
+use std::path::Path;
+use std::rc::Rc;
+
+use markdown::mdast::Node;
+
+use crate::md::{diag, Diag, Loc};
+use crate::slide::*;
+
+// Deck files from decks, e.g. ones built in code. Things the format can't say
+// are left out: hyphenation dictionaries, which are only kept loaded, and
+// image paths with spaces in them or text that would be read back differently,
+// which are reported.
+pub fn deck(deck: &Deck) -> String {
+    let mut diags = Vec::new();
+    let out = deck_with(deck, &mut diags);
+    for d in diags {
+        eprintln!("{}", d);
+    }
+    out
+}
+
+// Same as deck, but collecting problems rather than printing them. They are
+// located at the line of the output where the directive would have gone.
+pub fn deck_with(deck: &Deck, diags: &mut Vec<Diag>) -> String {
+    let mut out = Vec::new();
+    let defaults = GlobalConfig::default();
+    let c = &deck.config;
+
+    for (cmd, value) in [
+        (".TTL", &deck.meta.title),
+        (".ATH", &deck.meta.author),
+        (".DTE", &deck.meta.date),
+        (".EVT", &deck.meta.event),
+    ] {
+        if let Some(v) = value.as_ref().filter(|v| writable_text(v, &out, diags)) {
+            out.push(format!("{} {}", cmd, v));
+        }
+    }
+    if c.padding != defaults.padding {
+        out.push(format!(".PAD {}", c.padding));
+    }
+    if let Some(m) = &c.margin {
+        if writable(&m.image, &out, diags) {
+            out.push(format!(".MAR {} {}", m.middle, m.image.display()));
+        }
+    }
+    if c.align != defaults.align {
+        out.push(format!(".ALN {}", align(c.align)));
+    }
+    if c.tracking != defaults.tracking {
+        out.push(format!(".TRK {}", c.tracking));
+    }
+    if !c.kerning {
+        out.push(".KRN off".to_string());
+    }
+    if c.fit != defaults.fit {
+        out.push(format!(".FIT {}", fit(c.fit)));
+    }
+    for (cmd, segments) in [(".HDR", &c.header), (".FTR", &c.footer)] {
+        if let Some(s) = segments.as_ref().filter(|s| writable_segments(s, &out, diags)) {
+            out.push(format!("{} {}", cmd, s.join(" | ")));
+        }
+    }
+
+    for s in &deck.slides {
+        if !out.is_empty() {
+            out.push(String::new());
+        }
+        let sld = if s.hidden { ".SLD!" } else { ".SLD" };
+        out.push(match s.title.as_ref().filter(|t| writable_text(t, &out, diags)) {
+            Some(t) => format!("{} {}", sld, t),
+            None => sld.to_string(),
+        });
+        if let Some(l) = s.layout.filter(|&l| l != SlideLayout::TwoColumn) {
+            out.push(format!(".LAY {}", layout(l)));
+        }
+        if let Some(f) = s.fit {
+            out.push(format!(".FIT {}", fit(f)));
+        }
+        if !s.header {
+            out.push(".HDR off".to_string());
+        }
+        if !s.footer {
+            out.push(".FTR off".to_string());
+        }
+        content(&s.content, s.layout == Some(SlideLayout::TwoColumn), &mut out, diags);
+    }
+
+    let mut out = out.join("\n");
+    out.push('\n');
+    out
+}
+
+// Paths are the rest of a directive's words, so they can't have spaces.
+fn writable(path: &Path, out: &[String], diags: &mut Vec<Diag>) -> bool {
+    if !path.to_string_lossy().contains(char::is_whitespace) {
+        return true;
+    }
+    left_out(out, diags, "unwritable-path", format!("Left out path with spaces: {}", path.display()));
+    false
+}
+
+// Directive text is read back a line at a time, with {{variables}} expanded.
+// Markdown can escape braces, but titles and metadata are shown as they are.
+fn writable_text(value: &str, out: &[String], diags: &mut Vec<Diag>) -> bool {
+    if !value.contains("{{") && !value.contains('\n') {
+        return true;
+    }
+    left_out(out, diags, "unwritable-text", format!("Left out text with {{{{ or a line break: {:?}", value));
+    false
+}
+
+// Segments are split at | and trimmed, and a lone "off" turns them off.
+fn writable_segments(segments: &[String], out: &[String], diags: &mut Vec<Diag>) -> bool {
+    let split = segments.is_empty() || segments == ["off"] || segments.iter().any(|s| s.contains('|') || s.trim() != s);
+    if split {
+        let msg = format!("Left out header or footer segments: {:?}", segments);
+        left_out(out, diags, "unwritable-text", msg);
+        return false;
+    }
+    segments.iter().all(|s| writable_text(s, out, diags))
+}
+
+fn left_out(out: &[String], diags: &mut Vec<Diag>, code: &'static str, msg: String) {
+    let loc = Loc { file: Rc::from("<deck>"), line: out.len() + 1 };
+    diag(diags, &loc, code, msg);
+}
+
+// The first grid of a two-column slide is the one opened by .LAY two-column.
+fn content(content: &[Content], mut two_column: bool, out: &mut Vec<String>, diags: &mut Vec<Diag>) {
+    for c in content {
+        match c {
+            Content::Md(n) => {
+                let md = markdown(n);
+                if !md.is_empty() {
+                    // A blank line keeps it apart from markdown before it.
+                    if out.last().is_some_and(|l| !l.starts_with('.') || l.starts_with("..")) {
+                        out.push(String::new());
+                    }
+                    out.extend(md.lines().map(|l| match l.starts_with('.') {
+                        true => format!(".{}", l),
+                        false => l.to_string(),
+                    }));
+                }
+            }
+            Content::Img(p) => {
+                if writable(p, out, diags) {
+                    out.push(format!(".IMG {}", p.display()));
+                }
+            }
+            Content::Align(a) => out.push(format!(".ALN {}", align(*a))),
+            Content::Grid(g) => {
+                if two_column {
+                    out.push(".LAY two-column".to_string());
+                    two_column = false;
+                } else {
+                    out.push(format!(".GRD {}", g.ratio));
+                }
+                self::content(&g.first, false, out, diags);
+                if g.split {
+                    out.push(".COL".to_string());
+                }
+                self::content(&g.second, false, out, diags);
+                if g.closed {
+                    out.push(".GRD end".to_string());
+                }
+            }
+        }
+    }
+}
+
+fn align(a: Align) -> &'static str {
+    match a {
+        Align::Left => "left",
+        Align::Center => "center",
+        Align::Right => "right",
+        Align::Justify => "justify",
+    }
+}
+
+fn fit(f: FitMode) -> &'static str {
+    match f {
+        FitMode::Off => "off",
+        FitMode::Spacing => "spacing",
+        FitMode::Shrink => "shrink",
+    }
+}
+
+fn layout(l: SlideLayout) -> &'static str {
+    match l {
+        SlideLayout::Content => "content",
+        SlideLayout::Title => "title",
+        SlideLayout::Section => "section",
+        SlideLayout::TwoColumn => "two-column",
+        SlideLayout::BigStatement => "big-statement",
+        SlideLayout::ImageFull => "image-full",
+    }
+}
+
+// Markdown source for a tree, escaped so that it parses back into the same
+// one. Blocks are separated by blank lines.
+pub fn markdown(node: &Node) -> String {
+    match node {
+        Node::Root(r) => blocks(&r.children, "\n\n"),
+        n => block(n),
+    }
+}
+
+fn blocks(nodes: &[Node], sep: &str) -> String {
+    nodes.iter().map(block).filter(|b| !b.is_empty()).collect::<Vec<_>>().join(sep)
+}
+
+fn block(node: &Node) -> String {
+    match node {
+        Node::Paragraph(p) => inline(&p.children),
+        Node::Heading(h) => format!("{} {}", "#".repeat(h.depth as usize), inline(&h.children)),
+        Node::BlockQuote(q) => prefix(&blocks(&q.children, "\n\n"), ">", ">"),
+        Node::List(l) => {
+            let sep = if l.spread { "\n\n" } else { "\n" };
+            let items = l.children.iter().enumerate().map(|(i, item)| {
+                let marker = match l.ordered {
+                    true => format!("{}.", l.start.unwrap_or(1) as usize + i),
+                    false => "-".to_string(),
+                };
+                let body = match item {
+                    Node::ListItem(li) => blocks(&li.children, if li.spread { "\n\n" } else { "\n" }),
+                    n => block(n),
+                };
+                prefix(&body, &marker, &" ".repeat(marker.len()))
+            });
+            items.collect::<Vec<_>>().join(sep)
+        }
+        Node::Code(c) => {
+            let fence = "`".repeat(longest_run(&c.value, '`').max(2) + 1);
+            let lang = c.lang.as_deref().unwrap_or("");
+            format!("{}{}\n{}\n{}", fence, lang, c.value, fence)
+        }
+        Node::ThematicBreak(_) => "***".to_string(),
+        n => inline(std::slice::from_ref(n)),
+    }
+}
+
+// Put first before the first line of text, and rest before the others.
+fn prefix(text: &str, first: &str, rest: &str) -> String {
+    if text.is_empty() {
+        return first.to_string();
+    }
+    let lines = text.lines().enumerate().map(|(i, l)| {
+        let p = if i == 0 { first } else { rest };
+        match l.is_empty() {
+            true => p.trim_end().to_string(),
+            false => format!("{} {}", p, l),
+        }
+    });
+    lines.collect::<Vec<_>>().join("\n")
+}
+
+fn inline(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for n in nodes {
+        match n {
+            Node::Text(t) => escape(&t.value, out.is_empty() || out.ends_with('\n'), &mut out),
+            Node::Strong(s) => out += &format!("**{}**", inline(&s.children)),
+            Node::Emphasis(e) => out += &format!("*{}*", inline(&e.children)),
+            Node::InlineCode(c) => {
+                let ticks = "`".repeat(longest_run(&c.value, '`') + 1);
+                out += &format!("{} {} {}", ticks, c.value, ticks);
+            }
+            Node::Break(_) => out += "\\\n",
+            Node::Html(h) => out += &h.value,
+            n => escape(&n.to_string(), out.is_empty() || out.ends_with('\n'), &mut out),
+        }
+    }
+    out
+}
+
+fn longest_run(s: &str, c: char) -> usize {
+    s.split(|x| x != c).map(|r| r.len()).max().unwrap_or(0)
+}
+
+// Backslash out anything markdown would read as markup, and at the start of a
+// line, anything that would begin a block. Braces are escaped as well, so that
+// text can't be taken for a {{variable}}.
+fn escape(text: &str, mut line_start: bool, out: &mut String) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if line_start && c.is_ascii_digit() {
+            out.push(c);
+            while let Some(d) = chars.next_if(char::is_ascii_digit) {
+                out.push(d);
+            }
+            if let Some(p) = chars.next_if(|&p| p == '.' || p == ')') {
+                out.push('\\');
+                out.push(p);
+            }
+            line_start = false;
+            continue;
+        }
+        if "\\`*_[]<!&{}~|".contains(c) || (line_start && "-+=#>".contains(c)) {
+            out.push('\\');
+        }
+        out.push(c);
+        line_start = c == '\n';
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::*;
+    use crate::md;
+
+    fn parse(name: &str, text: &str) -> Deck {
+        let path = env::temp_dir().join(format!("eileda-{}-{}.eimd", name, process::id()));
+        fs::write(&path, text).unwrap();
+        let mut diags = Vec::new();
+        let items = md::lex_with(path.to_str().unwrap(), &mut diags);
+        fs::remove_file(&path).unwrap();
+        let deck = md::parse_with(&items, &mut diags);
+        assert!(diags.iter().all(|d| d.code == "missing-image"), "{}", text);
+        deck
+    }
+
+    // Write a deck out, and parse it back in.
+    fn round_trip(name: &str, deck: &Deck) -> (String, Deck) {
+        let text = deck.to_eimd();
+        let parsed = parse(name, &text);
+        (text, parsed)
+    }
+
+    // Text of the non-blank markdown in some content.
+    fn texts(content: &[Content]) -> Vec<String> {
+        content
+            .iter()
+            .filter_map(|c| match c {
+                Content::Md(n) if !md::is_blank(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn escapes_text() {
+        let lines = [".IMG not.png", "..two dots", "# not a heading", "*not emphasis*", "1. not a list", "a <b> & {c}"];
+        let deck = lines.iter().fold(Deck::new(), |d, l| d.slide("Text", |s| s.text(l)));
+        let (text, parsed) = round_trip("escape", &deck);
+
+        assert_eq!(parsed.slides.len(), lines.len());
+        for (s, l) in parsed.slides.iter().zip(lines) {
+            assert_eq!(texts(&s.content), [l], "{}", text);
+        }
+        assert_eq!(parsed.to_eimd(), text);
+    }
+
+    #[test]
+    fn keeps_markdown() {
+        let src = "- **40%** faster\n- `fn` calls\n\n> quoted\n\n.leading dot";
+        let deck = Deck::new().slide("Md", |s| s.markdown(src));
+        let (text, parsed) = round_trip("markdown", &deck);
+        assert_eq!(texts(&parsed.slides[0].content), texts(&deck.slides[0].content));
+        assert_eq!(parsed.to_eimd(), text);
+    }
+
+    #[test]
+    fn keeps_grids_and_images() {
+        let deck = Deck::new()
+            .slide("Grid", |s| s.text("Above").grid(40, |g| g.text("Left").col().image("bench.png")).text("Below"))
+            .slide("Columns", |s| s.layout(SlideLayout::TwoColumn).text("One").col().text("Two"));
+        let (text, parsed) = round_trip("grid", &deck);
+
+        let c = &parsed.slides[0].content;
+        assert_eq!(texts(c), ["Above", "Below"]);
+        match c.iter().find(|c| matches!(c, Content::Grid(_))) {
+            Some(Content::Grid(g)) => {
+                assert_eq!((g.ratio, g.split, g.closed), (40, true, true));
+                assert_eq!(texts(&g.first), ["Left"]);
+                assert!(matches!(&g.second[..], [Content::Img(p)] if p.to_str() == Some("bench.png")));
+            }
+            _ => panic!("{}", text),
+        }
+
+        assert_eq!(parsed.slides[1].layout, Some(SlideLayout::TwoColumn));
+        match &parsed.slides[1].content[..] {
+            [Content::Grid(g)] => {
+                assert_eq!((texts(&g.first), texts(&g.second)), (vec!["One".to_string()], vec!["Two".to_string()]));
+            }
+            _ => panic!("{}", text),
+        }
+        assert_eq!(parsed.to_eimd(), text);
+    }
+
+    #[test]
+    fn keeps_closed_two_column_grids() {
+        let deck = parse("closed", ".SLD Closed\n.LAY two-column\nOne\n.COL\nTwo\n.GRD end\nBelow\n");
+        let (text, parsed) = round_trip("closed-out", &deck);
+
+        assert!(text.contains(".LAY two-column") && !text.contains(".GRD 50"), "{}", text);
+        assert_eq!(parsed.slides[0].layout, Some(SlideLayout::TwoColumn));
+        assert_eq!(texts(&parsed.slides[0].content), ["Below"]);
+        assert_eq!(parsed.to_eimd(), text);
+    }
+
+    #[test]
+    fn keeps_metadata() {
+        let deck = Deck::new()
+            .title("Weekly")
+            .author("Ada")
+            .date("2024-05-01")
+            .event("Standup")
+            .padding(24)
+            .footer(&["%n/%N", "", "%t"])
+            .slide("One", |s| s.hidden().no_header());
+        let (text, parsed) = round_trip("meta", &deck);
+
+        let m = &parsed.meta;
+        assert_eq!(m.title.as_deref(), Some("Weekly"));
+        assert_eq!(m.author.as_deref(), Some("Ada"));
+        assert_eq!(m.date.as_deref(), Some("2024-05-01"));
+        assert_eq!(m.event.as_deref(), Some("Standup"));
+        assert_eq!(parsed.config.padding, 24);
+        assert_eq!(parsed.config.footer, deck.config.footer);
+        assert!(parsed.slides[0].hidden && !parsed.slides[0].header);
+        assert_eq!(parsed.to_eimd(), text);
+    }
+
+    #[test]
+    fn leaves_out_paths_with_spaces() {
+        let deck = Deck::new().margin(400, "side bar.png").slide("Img", |s| s.image("my bench.png").text("After"));
+        let mut diags = Vec::new();
+        let text = deck.to_eimd_with(&mut diags);
+        assert_eq!(diags.len(), 2);
+        assert!(diags.iter().all(|d| d.code == "unwritable-path"));
+        assert!(!text.contains(".IMG") && !text.contains(".MAR"));
+    }
+
+    #[test]
+    fn keeps_titles_as_given() {
+        let deck = Deck::new()
+            .title("@eileda: 50% {faster}")
+            .footer(&["%n", "@team", ".end"])
+            .slide("@scale results", |s| s.text("{{not a variable}}"))
+            .slide(".SLD in a title", |s| s);
+        let (text, parsed) = round_trip("titles", &deck);
+
+        assert_eq!(parsed.meta.title, deck.meta.title);
+        assert_eq!(parsed.config.footer, deck.config.footer);
+        assert_eq!(parsed.slides[0].title.as_deref(), Some("@scale results"));
+        assert_eq!(texts(&parsed.slides[0].content), ["{{not a variable}}"]);
+        assert_eq!(parsed.slides[1].title.as_deref(), Some(".SLD in a title"));
+        assert_eq!(parsed.to_eimd(), text);
+    }
+
+    #[test]
+    fn leaves_out_text_read_back_differently() {
+        let deck = Deck::new()
+            .title("Costs in {{currency}}")
+            .author("Ada\nLovelace")
+            .header(&["left | right"])
+            .footer(&["off"])
+            .slide("{{quarter}} results", |s| s.text("Body"));
+        let mut diags = Vec::new();
+        let text = deck.to_eimd_with(&mut diags);
+
+        assert_eq!(diags.len(), 5);
+        assert!(diags.iter().all(|d| d.code == "unwritable-text"));
+        let parsed = parse("unwritable", &text);
+        assert!(parsed.meta.title.is_none() && parsed.meta.author.is_none());
+        assert!(parsed.config.header.is_none() && parsed.config.footer.is_none());
+        assert!(parsed.slides[0].title.is_none());
+    }
+}